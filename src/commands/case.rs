@@ -0,0 +1,169 @@
+use serenity::futures::Stream;
+use poise::serenity_prelude as serenity;
+
+use crate::{Context, Error};
+use crate::cache::format_age;
+use crate::guild::guild_strategy;
+use crate::health::Dataset;
+use crate::unboxing::{evaluate, STATTRAK_CHANCE};
+
+/// Number of most valuable drops listed
+const TOP_DROPS: usize = 5;
+
+async fn autocomplete_container<'a>(
+    ctx: Context<'a>,
+    partial: &'a str,
+) -> impl Stream<Item = String> + 'a {
+    let Ok(store) = ctx.data().items().await else {
+        return serenity::futures::stream::iter(vec![]);
+    };
+
+    let matches: Vec<String> = store.search
+        .search(partial, 15, |name| store.item_data.get(name).is_some_and(|priced| priced.info.is_container()))
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+
+    serenity::futures::stream::iter(matches)
+}
+
+fn format_chance(chance: f64) -> String {
+    match chance * 100.0 {
+        percent if percent >= 1.0 => format!("{:.2}%", percent),
+        percent if percent >= 0.01 => format!("{:.3}%", percent),
+        percent => format!("{:.5}%", percent),
+    }
+}
+
+/// Expected value of opening a case or capsule
+#[poise::command(
+    slash_command,
+    category = "Items",
+)]
+pub async fn case(
+    ctx: Context<'_>,
+    #[description = "Case, capsule or souvenir package"] #[rest]
+    #[autocomplete = "autocomplete_container"]
+    container: String
+) -> Result<(), Error> {
+    let author_id = ctx.author().id.get() as i64;
+    let db = ctx.data().db()?.lock().await;
+    let store = ctx.data().items().await?;
+    let currencies = ctx.data().currencies()?;
+
+    let found = store.item_data
+        .get(container.as_str())
+        .filter(|priced| priced.info.is_container());
+
+    let reply = if let Some(found) = found {
+        let author_user = db.get_user(&author_id).await?.unwrap();
+        let strategy = guild_strategy(&ctx, &db).await?;
+        let currency = &author_user.currency;
+
+        let value = evaluate(&found.info, &store, strategy.as_ref());
+
+        let cost = match (value.case_price, value.key_price) {
+            (Some(case_price), Some(key_price)) => format!(
+                "{}\n{} case + {} key",
+                currencies.exchange(case_price + key_price, currency),
+                currencies.exchange(case_price, currency),
+                currencies.exchange(key_price, currency)
+            ),
+            (Some(case_price), None) => currencies.exchange(case_price, currency),
+            (None, _) => "Unknown".to_string(),
+        };
+
+        let mut embed = serenity::CreateEmbed::default()
+            .title(container.clone())
+            .color(serenity::Color::from_rgb(254, 171, 26))
+            .field("Expected value", currencies.exchange(value.expected, currency), true)
+            .field("Cost per opening", cost, true)
+            .to_owned();
+
+        if let (Some(cost), Some(roi)) = (value.cost(), value.roi()) {
+            let net = value.expected - cost;
+            let net_string = if net < 0.0 {
+                format!("-{}", currencies.exchange(-net, currency))
+            } else {
+                format!("+{}", currencies.exchange(net, currency))
+            };
+
+            embed = embed
+                .field("Net per opening", net_string, true)
+                .field("ROI", format!("{:+.1}%", roi * 100.0), true);
+
+            if let Some(profit_chance) = value.profit_chance() {
+                embed = embed.field("Profit chance", format_chance(profit_chance), true);
+            }
+        }
+
+        let mut odds_string = String::new();
+        for grade in &value.grades {
+            odds_string.push_str(&format!(
+                "**{}** · {} · avg {}\n",
+                grade.name,
+                format_chance(grade.chance),
+                match grade.average {
+                    Some(average) => currencies.exchange(average, currency),
+                    None => "unpriced".to_string(),
+                }
+            ));
+        }
+        if !odds_string.is_empty() {
+            embed = embed.field("Odds", odds_string, false);
+        }
+
+        let mut drops_string = String::new();
+        for outcome in value.outcomes.iter().take(TOP_DROPS) {
+            drops_string.push_str(&format!(
+                "{} · **{}** · {}\n",
+                outcome.name,
+                currencies.exchange(outcome.price, currency),
+                format_chance(outcome.chance)
+            ));
+        }
+        if !drops_string.is_empty() {
+            embed = embed.field("Most valuable drops", drops_string, false);
+        }
+
+        if value.priced_drops < value.total_drops {
+            embed = embed.field(
+                ":warning:  Incomplete prices",
+                format!("Only **{}** of **{}** drops could be priced, unpriced ones count at the average of their grade and grades without any price are left out of the expected value",
+                    value.priced_drops,
+                    value.total_drops
+                ),
+                false
+            );
+        }
+
+        if let Some(notice) = ctx.data().health.notice(&[Dataset::Prices, Dataset::ExchangeRates]) {
+            embed = embed.field(":warning:  Degraded service", notice, false);
+        }
+
+        if let Some(image) = &found.info.image {
+            embed = embed.thumbnail(image);
+        }
+
+        let mut footer = format!(
+            "Suggested price: {} · Prices updated {}",
+            strategy.as_ref().unwrap_or(&ctx.data().config.price_strategy),
+            format_age(store.prices_fetched_at)
+        );
+        if value.stattrak {
+            footer = format!("StatTrak™ chance {:.0}% · {}", STATTRAK_CHANCE * 100.0, footer);
+        }
+
+        poise::CreateReply::default().embed(embed.footer(serenity::CreateEmbedFooter::new(footer)))
+    } else {
+        let embed = serenity::CreateEmbed::default()
+            .title(":x:  Container could not be found")
+            .color(serenity::Color::RED)
+            .to_owned();
+
+        poise::CreateReply::default().embed(embed)
+    };
+
+    ctx.send(reply).await?;
+    Ok(())
+}
@@ -1,8 +1,11 @@
 use std::cmp::Ordering;
 
 use crate::database::models::RoleAssignment;
+use crate::database::DatabaseManager;
+use crate::strategy::{Strategy, STRATEGY_EXAMPLES};
 use crate::{Context, Error};
 use poise::serenity_prelude as serenity;
+use serenity::futures::{Stream, StreamExt};
 
 const NOT_GUILD_MSG: &str = "Command can only be used in a guild";
 
@@ -27,7 +30,7 @@ pub async fn invroles(_ctx: Context<'_>) -> Result<(), Error> {
 )]
 pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
     let guild_id = ctx.guild_id().expect(NOT_GUILD_MSG).get() as i64;
-    let db = ctx.data().db.lock().await;
+    let db = ctx.data().db()?.lock().await;
 
     let mut embed = serenity::CreateEmbed::default().to_owned();
 
@@ -79,13 +82,13 @@ pub async fn add(
     #[description = "USD amount to assign at"] threshold: f64,
 ) -> Result<(), Error> {
     let guild_id = ctx.guild_id().expect(NOT_GUILD_MSG).get() as i64;
-    let db = ctx.data().db.lock().await;
+    let db = ctx.data().db()?.lock().await;
 
     let mut embed = serenity::CreateEmbed::default().to_owned();
 
     if let Some(mut guild) = db.get_guild(&guild_id).await? {
         guild.roles.push(RoleAssignment {
-            threshold: threshold,
+            threshold,
             role_id: role.id.get() as i64,
         });
 
@@ -130,7 +133,7 @@ pub async fn remove(
     #[description = "Role to remove"] role: serenity::Role,
 ) -> Result<(), Error> {
     let guild_id = ctx.guild_id().expect(NOT_GUILD_MSG).get() as i64;
-    let db = ctx.data().db.lock().await;
+    let db = ctx.data().db()?.lock().await;
 
     let mut embed = serenity::CreateEmbed::default().to_owned();
 
@@ -165,3 +168,75 @@ pub async fn remove(
 
     Ok(())
 }
+
+/// Suggested price strategy override of the current guild, if one is set and valid
+pub async fn guild_strategy(ctx: &Context<'_>, db: &DatabaseManager) -> Result<Option<Strategy>, Error> {
+    if let Some(guild_id) = ctx.guild_id() {
+        if let Some(guild) = db.get_guild(&(guild_id.get() as i64)).await? {
+            return Ok(guild.strategy.and_then(|strategy| strategy.parse().ok()));
+        }
+    }
+
+    Ok(None)
+}
+
+async fn autocomplete_strategy<'a>(
+    _ctx: Context<'a>,
+    partial: &'a str,
+) -> impl Stream<Item = String> + 'a {
+    serenity::futures::stream::iter(STRATEGY_EXAMPLES)
+        .filter(move |name| serenity::futures::future::ready(name.starts_with(&partial.to_lowercase())))
+        .map(|name| name.to_string())
+}
+
+/// Set how suggested prices are calculated
+#[poise::command(
+    slash_command,
+    guild_only,
+    category = "Guild settings",
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn pricestrategy(
+    ctx: Context<'_>,
+    #[description = "Strategy to use, leave empty for the default"]
+    #[autocomplete = "autocomplete_strategy"]
+    strategy: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().expect(NOT_GUILD_MSG).get() as i64;
+    let db = ctx.data().db()?.lock().await;
+
+    let mut embed = serenity::CreateEmbed::default().to_owned();
+
+    let parsed = strategy.as_deref().map(str::parse::<Strategy>).transpose();
+
+    match (parsed, db.get_guild(&guild_id).await?) {
+        (Ok(parsed), Some(mut guild)) => {
+            guild.strategy = strategy;
+            db.update_guild(&guild).await?;
+
+            let shown = parsed.unwrap_or_else(|| ctx.data().config.price_strategy.clone());
+
+            embed = embed
+                .title("Price strategy modified")
+                .color(serenity::Color::from_rgb(255, 255, 255))
+                .field("Suggested prices now use", shown.to_string(), false);
+        },
+        (Err(e), _) => {
+            embed = embed
+                .title(":x:  Invalid strategy")
+                .description(format!("{}\nAvailable: `{}`", e, STRATEGY_EXAMPLES.join("`, `")))
+                .color(serenity::Color::RED)
+        },
+        (_, None) => {
+            embed = embed
+                .title(":x:  Error: Something unexpected occurred")
+                .color(serenity::Color::RED)
+        }
+    }
+
+    let reply = poise::CreateReply::default().embed(embed);
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
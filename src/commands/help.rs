@@ -16,10 +16,15 @@ pub async fn help( ctx: Context<'_>) -> Result<(), Error> {
             .color(serenity::Color::from((38, 59, 127)))
             .fields(vec![
                 ("Pricecheck Items", "`/price`", true),
+                ("Price History", "`/pricehistory`", true),
+                ("Item Details", "`/item`", true),
+                ("Case Value", "`/case`", true),
+                ("Trade-ups", "`/tradeup`", true),
                 ("Pricecheck Inventory", "`/inv`", true),
                 ("Set currency", "`/currency`", true),
                 ("Unlink steam", "`/currency`", true),
-                ("Server settings", "`/invroles`", true),
+                ("Server settings", "`/invroles` `/pricestrategy`", true),
+                ("Bot status", "`/status`", true),
                 ("Support Server", "[Join Server](https://discord.gg/hh9v4eF)", true)
             ])
             .to_owned();
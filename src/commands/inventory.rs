@@ -3,27 +3,63 @@ use std::collections::HashMap;
 use serde::Deserialize;
 
 use crate::{Context, Error};
-use crate::Priced;
-use crate::currency::exchange;
+use crate::priced_items::PricedItems;
+use crate::applied::{parse_applied, AppliedKind};
+use crate::cache::format_age;
+use crate::doppler::phased_name;
+use crate::guild::guild_strategy;
+use crate::health::Dataset;
+use crate::store::ItemStore;
+use crate::strategy::Strategy;
 
 use poise::serenity_prelude as serenity;
 
 #[derive(Deserialize, Debug)]
 pub struct SteamWebAsset {
+    #[serde(default)]
+    pub assetid: String,
     pub classid: String,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct SteamAssetProperty {
+    pub propertyid: u32,
+    #[serde(default)]
+    pub int_value: Option<String>,
+}
+
+/// Per-asset properties Steam lists next to the assets, property 1 is the paint seed
+#[derive(Deserialize, Debug)]
+pub struct SteamAssetProperties {
+    pub assetid: String,
+    #[serde(default)]
+    pub asset_properties: Vec<SteamAssetProperty>,
+}
+
+const PAINT_SEED_PROPERTY: u32 = 1;
+
+/// One line of an item's description, stickers and charms are listed in these
+#[derive(Deserialize, Debug)]
+pub struct SteamWebDescriptionLine {
+    #[serde(default)]
+    pub value: String,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct SteamWebDescription {
     pub classid: String,
     pub market_hash_name: String,
     pub icon_url: String,
+    #[serde(default)]
+    pub descriptions: Vec<SteamWebDescriptionLine>,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct SteamWebResponse {
     pub descriptions: Vec<SteamWebDescription>,
     pub assets: Vec<SteamWebAsset>,
+    #[serde(default)]
+    pub asset_properties: Vec<SteamAssetProperties>,
 }
 
 
@@ -43,12 +79,30 @@ pub struct SteamSummaryResponse {
     pub response: SteamSummaryWrapper,
 }
 
+pub struct InventoryValue {
+    pub total: f64,
+    pub prices_fetched_at: Option<i64>,
+    pub count: i32,
+    /// Part of `total` coming from items with a low confidence price
+    pub low_confidence: f64,
+    /// Doppler items whose phase could not be identified, valued at the base price
+    pub unknown_phase: i32,
+    /// Items valued above the market price for their paint seed, and what that added to `total`
+    pub rare_patterns: i32,
+    pub pattern_premium: f64,
+    /// What applied stickers and charms add on top of `total`
+    pub craft: f64,
+    pub applied_count: i32,
+}
+
 async fn compute_inventory_value(
     ctx: &Context<'_>,
+    store: &ItemStore,
     steamid64: i64,
-) -> Result<(f64, i32), Box<dyn std::error::Error + Send + Sync>> {
-    let item_data: &HashMap<String, Priced> = &ctx.data().item_data;
-    let doppler_data: &HashMap<String, String> = &ctx.data().doppler_data;
+    strategy: Option<&Strategy>,
+) -> Result<InventoryValue, Box<dyn std::error::Error + Send + Sync>> {
+    let item_data: &PricedItems = &store.item_data;
+    let doppler_data: &HashMap<String, String> = &store.doppler_data;
 
     let steamweb: SteamWebResponse;
     {
@@ -68,31 +122,93 @@ async fn compute_inventory_value(
         .map(|desc| (desc.classid.clone(), desc))
         .collect();
 
+    let paint_seeds: HashMap<&str, u32> = steamweb.asset_properties
+        .iter()
+        .filter_map(|asset| {
+            let seed = asset.asset_properties
+                .iter()
+                .find(|property| property.propertyid == PAINT_SEED_PROPERTY)?
+                .int_value
+                .as_ref()?
+                .parse()
+                .ok()?;
+            Some((asset.assetid.as_str(), seed))
+        })
+        .collect();
+
     // 2. For each asset, lookup corresponding classid and compute price
     let mut total_value = 0.0;
+    let mut low_confidence_value = 0.0;
     // let mut total_count = 0;
     let mut total_success = 0;
+    let mut unknown_phase_count = 0;
+    let mut rare_pattern_count = 0;
+    let mut pattern_premium = 0.0;
+    let mut craft_value = 0.0;
+    let mut applied_count = 0;
+    let config = &ctx.data().config;
 
     for asset in &steamweb.assets {
         // total_count += 1;
         if let Some(description) = classid_lookup.get(&asset.classid) {
-            // this will need to be modified for dopplers
-            let mut modified_hash_name = description.market_hash_name.clone();
-
-            if let Some(doppler) = doppler_data.get(&description.icon_url) {
-                modified_hash_name += &format!(" {}", doppler);
-            }
+            let hash_name = &description.market_hash_name;
+
+            // Dopplers are priced per phase, unknown phases fall back to the base price entry
+            let price = doppler_data.get(&description.icon_url)
+                .and_then(|doppler| item_data.get(phased_name(hash_name, doppler).as_str()))
+                .or_else(|| item_data.get(hash_name.as_str()));
+
+            if let Some(price) = price {
+                if let Some(mut value) = price.suggested(strategy) {
+                    let seed = paint_seeds.get(asset.assetid.as_str()).copied();
+                    if let Some(premium) = seed.and_then(|seed| store.patterns.lookup(hash_name, seed)) {
+                        pattern_premium += value * (premium.multiplier - 1.0);
+                        rare_pattern_count += 1;
+                        value *= premium.multiplier;
+                    }
 
-            if let Some(price) = item_data.get(&modified_hash_name) {
-                if let Some(value) = price.feather {
                     total_value += value;
                     total_success += 1;
+
+                    if price.phase_fallback {
+                        unknown_phase_count += 1;
+                    }
+
+                    if price.confidence.is_low() {
+                        low_confidence_value += value;
+                    }
                 }
             }
+
+            let applied = description.descriptions
+                .iter()
+                .flat_map(|line| parse_applied(&line.value));
+
+            for (kind, name) in applied {
+                let Some(value) = item_data.get(name.as_str()).and_then(|priced| priced.suggested(strategy)) else {
+                    continue;
+                };
+
+                craft_value += match kind {
+                    AppliedKind::Sticker => config.sticker_value.apply(value),
+                    AppliedKind::Charm => config.charm_value.apply(value),
+                };
+                applied_count += 1;
+            }
         }
     }
 
-    Ok((total_value, total_success))
+    Ok(InventoryValue {
+        total: total_value,
+        prices_fetched_at: store.prices_fetched_at,
+        count: total_success,
+        low_confidence: low_confidence_value,
+        unknown_phase: unknown_phase_count,
+        rare_patterns: rare_pattern_count,
+        pattern_premium,
+        craft: craft_value,
+        applied_count,
+    })
 }
 
 
@@ -131,7 +247,8 @@ pub async fn inv(
     let author_id = ctx.author().id.get() as i64;
     let is_self: bool = user_id == author_id;
 
-    let db = ctx.data().db.lock().await;
+    let db = ctx.data().db()?.lock().await;
+    let currencies = ctx.data().currencies()?;
 
     if let (Some(target_user), Some(author_user)) = (db.get_user(&user_id).await?, db.get_user(&author_id).await?) {
         // Need to pull author_user to do potential currency conversion
@@ -153,8 +270,15 @@ pub async fn inv(
         } else {
             // Steam account is linked, check if we can evaluate or not
 
-            match compute_inventory_value(&ctx, target_user.steam_id).await {
-                Ok((inv_value, item_count)) => {
+            let strategy = guild_strategy(&ctx, &db).await?;
+
+            // Fetched up front so a store that is still loading gets the warming up reply
+            // rather than being mistaken for a private inventory
+            let store = ctx.data().items().await?;
+
+            match compute_inventory_value(&ctx, &store, target_user.steam_id, strategy.as_ref()).await {
+                Ok(value) => {
+                    let inv_value = value.total;
                     let steam_summary: SteamSummaryResponse;
                     {
                         let steam_token = &ctx.data().config.steam_token;
@@ -173,12 +297,62 @@ pub async fn inv(
                         .thumbnail(steam_player.avatarfull.clone())
                         .color(serenity::Color::from_rgb(254, 171, 26))
                         .field(
-                            format!("CS2 Inventory Value ({})", author_user.currency),
+                            format!("CS2 Inventory Value ({})", currencies.shown_code(&author_user.currency)),
                             format!("**{}** items worth **{}**\n Powered by [Skinpock.com](https://www.skinpock.com/)",
-                                item_count,
-                                exchange(inv_value, &author_user.currency, &ctx).await
+                                value.count,
+                                currencies.exchange(inv_value, &author_user.currency)
+                            ),
+                        false);
+
+                    embed = embed.footer(serenity::CreateEmbedFooter::new(format!(
+                        "Prices updated {} · Exchange rates updated {}",
+                        format_age(value.prices_fetched_at),
+                        format_age(currencies.fetched_at)
+                    )));
+
+                    if value.rare_patterns > 0 {
+                        embed = embed.field(
+                            "Rare patterns",
+                            format!("**{}** items have a sought-after paint seed, adding **{}** over their market price",
+                                value.rare_patterns,
+                                currencies.exchange(value.pattern_premium, &author_user.currency)
+                            ),
+                        false);
+                    }
+
+                    if value.applied_count > 0 {
+                        embed = embed.field(
+                            "Craft value",
+                            format!("**{}** applied stickers and charms add **{}**, for **{}** in total",
+                                value.applied_count,
+                                currencies.exchange(value.craft, &author_user.currency),
+                                currencies.exchange(inv_value + value.craft, &author_user.currency)
                             ),
                         false);
+                    }
+
+                    if let Some(notice) = ctx.data().health.notice(&[Dataset::Prices, Dataset::Dopplers, Dataset::ExchangeRates]) {
+                        embed = embed.field(":warning:  Degraded service", notice, false);
+                    }
+
+                    if value.unknown_phase > 0 {
+                        embed = embed.field(
+                            ":warning:  Unknown Doppler phases",
+                            format!("**{}** Doppler items could not be matched to a phase and are valued at the base price across all phases",
+                                value.unknown_phase
+                            ),
+                        false);
+                    }
+
+                    if value.low_confidence > 0.0 {
+                        embed = embed.field(
+                            "Low confidence prices",
+                            format!("**{}** ({:.0}%) of this value comes from items with few or disagreeing market quotes",
+                                currencies.exchange(value.low_confidence, &author_user.currency),
+                                value.low_confidence / inv_value * 100.0
+                            ),
+                        false);
+                    }
 
                     let server_id = ctx.guild_id().map(|id| id.get()).unwrap_or(0);
                     let referral_code = if server_id == 727970463325749268 {
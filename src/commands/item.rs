@@ -0,0 +1,162 @@
+use crate::items::{variant_name, Item, Named, Reference};
+use crate::price::autocomplete_item;
+use crate::{Context, Error};
+use poise::serenity_prelude as serenity;
+
+/// Longest list of collections or containers shown before it is cut short
+const MAX_REFERENCES: usize = 5;
+
+/// Drops the `<i>`/`<br>` markup found in catalog descriptions
+fn clean_description(description: &str) -> String {
+    let mut cleaned = String::with_capacity(description.len());
+    let mut in_tag = false;
+
+    for c in description.replace("\\n", "\n").chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => cleaned.push(c),
+            _ => {},
+        }
+    }
+
+    cleaned.trim().to_string()
+}
+
+fn list_references(references: &[Reference]) -> String {
+    let mut names: Vec<&str> = references
+        .iter()
+        .take(MAX_REFERENCES)
+        .map(|reference| reference.name.as_str())
+        .collect();
+
+    let more = references.len().saturating_sub(MAX_REFERENCES);
+    let more_string = format!("and {} more", more);
+    if more > 0 {
+        names.push(&more_string);
+    }
+
+    names.join("\n")
+}
+
+fn item_fields(item: &Item, stattrak: bool, souvenir: bool) -> Vec<(&'static str, String, bool)> {
+    let mut fields = vec![];
+
+    let name = |named: &Option<Named>| named.as_ref().and_then(|named| named.name.clone());
+
+    if let Some(weapon) = name(&item.weapon) {
+        fields.push(("Weapon", weapon, true));
+    }
+    if let Some(category) = name(&item.category) {
+        fields.push(("Category", category, true));
+    }
+    if let Some(kind) = &item.kind {
+        fields.push(("Type", kind.clone(), true));
+    }
+    if let Some(pattern) = name(&item.pattern) {
+        fields.push(("Finish", pattern, true));
+    }
+    if let Some(rarity) = item.rarity.as_ref().and_then(|rarity| rarity.name.clone()) {
+        fields.push(("Rarity", rarity, true));
+    }
+    if let Some(phase) = &item.phase {
+        fields.push(("Phase", phase.clone(), true));
+    }
+
+    match (name(&item.wear), item.min_float, item.max_float) {
+        (wear, Some(min), Some(max)) => fields.push((
+            "Wear",
+            format!("{}{:.2} – {:.2}", wear.map(|wear| format!("{}\n", wear)).unwrap_or_default(), min, max),
+            true
+        )),
+        (Some(wear), _, _) => fields.push(("Wear", wear, true)),
+        _ => {},
+    }
+
+    if let Some(paint_index) = &item.paint_index {
+        fields.push(("Finish index", paint_index.clone(), true));
+    }
+
+    if item.weapon.is_some() {
+        let variants = match (stattrak, souvenir) {
+            (true, true) => "StatTrak™ and Souvenir",
+            (true, false) => "StatTrak™",
+            (false, true) => "Souvenir",
+            (false, false) => "None",
+        };
+        fields.push(("Variants", variants.to_string(), true));
+    }
+
+    if !item.collections.is_empty() {
+        fields.push(("Collection", list_references(&item.collections), false));
+    }
+    if !item.crates.is_empty() {
+        fields.push(("Found in", list_references(&item.crates), false));
+    }
+
+    if item.is_container() {
+        let mut contents = format!("**{}** items", item.contains.len());
+        if !item.contains_rare.is_empty() {
+            contents.push_str(&format!(" and **{}** rare special items", item.contains_rare.len()));
+        }
+        fields.push(("Contents", contents, false));
+    }
+
+    fields
+}
+
+/// Show catalog details of a CS2 item
+#[poise::command(
+    slash_command,
+    category = "Items",
+)]
+pub async fn item(
+    ctx: Context<'_>,
+    #[description = "Item name"] #[rest]
+    #[autocomplete = "autocomplete_item"]
+    item_name: String
+) -> Result<(), Error> {
+    let store = ctx.data().items().await?;
+
+    let reply = if let Some(found) = store.item_data.get(item_name.as_str()) {
+        let item = &found.info;
+
+        // StatTrak and Souvenir copies are separate catalog entries, look them up by name
+        let hash_name = item.market_hash_name.as_deref().unwrap_or(&item_name);
+        let variant = |prefix: &str| store.item_data.contains_key(variant_name(hash_name, prefix).as_str());
+        let stattrak = item.stattrak || variant("StatTrak™ ");
+        let souvenir = item.souvenir || variant("Souvenir ");
+
+        let color = match item.rarity_rgb() {
+            Some((r, g, b)) => serenity::Color::from_rgb(r, g, b),
+            None => serenity::Color::LIGHT_GREY,
+        };
+
+        let mut embed = serenity::CreateEmbed::default()
+            .title(item_name.clone())
+            .color(color)
+            .fields(item_fields(item, stattrak, souvenir))
+            .footer(serenity::CreateEmbedFooter::new("Use /price for market prices"))
+            .to_owned();
+
+        if let Some(description) = item.description.as_deref().map(clean_description).filter(|d| !d.is_empty()) {
+            embed = embed.description(description);
+        }
+
+        if let Some(image) = &item.image {
+            embed = embed.thumbnail(image);
+        }
+
+        poise::CreateReply::default().embed(embed)
+    } else {
+        let embed = serenity::CreateEmbed::default()
+            .title(":x:  Item could not be found")
+            .color(serenity::Color::RED)
+            .to_owned();
+
+        poise::CreateReply::default().embed(embed)
+    };
+
+    ctx.send(reply).await?;
+    Ok(())
+}
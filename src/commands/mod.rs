@@ -1,5 +1,10 @@
 pub mod help;
 pub mod price;
+pub mod pricehistory;
 pub mod inventory;
 pub mod guild;
-pub mod utility;
\ No newline at end of file
+pub mod utility;
+pub mod status;
+pub mod item;
+pub mod case;
+pub mod tradeup;
\ No newline at end of file
@@ -1,31 +1,101 @@
-use serenity::futures::{Stream, StreamExt};
+use std::time::Duration;
+
+use serenity::futures::Stream;
 use poise::serenity_prelude as serenity;
 use urlencoding::encode;
 
 use crate::{Context, Error};
-use crate::currency::exchange;
-
-fn smart_search(item: &str, query: &str) -> bool {
-    let item_lower = item.to_lowercase();
-    let query_lower = query.to_lowercase();
-    let query_words: Vec<&str> = query_lower.split_whitespace().collect();
+use crate::cache::format_age;
+use crate::guild::guild_strategy;
+use crate::health::Dataset;
+use crate::search::Lookup;
+use crate::store::ItemStore;
 
-    query_words.iter().all(|&word| {
-        item_lower.split(|c: char| !c.is_alphanumeric())
-            .any(|item_part| item_part.contains(word))
-    })
-}
+/// Discord cuts button labels after this many characters
+const LABEL_LIMIT: usize = 80;
 
-async fn autocomplete_item<'a>(
+pub async fn autocomplete_item<'a>(
     ctx: Context<'a>,
     partial: &'a str,
 ) -> impl Stream<Item = String> + 'a {
-    let hash_names = &ctx.data().all_hash_names;
+    let Ok(store) = ctx.data().items().await else {
+        return serenity::futures::stream::iter(vec![]);
+    };
+
+    let matches: Vec<String> = store.search
+        .search(partial, 15, |_| true)
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+
+    serenity::futures::stream::iter(matches)
+}
+
+/// The item a typed name refers to, asking the user to pick from the closest names when it is
+/// ambiguous. `None` once the user has been answered without picking.
+async fn resolve_item(ctx: Context<'_>, store: &ItemStore, item_name: String) -> Result<Option<String>, Error> {
+    // Autocompleted names are exact
+    if store.item_data.contains_key(item_name.as_str()) {
+        return Ok(Some(item_name));
+    }
+
+    let suggestions = match store.search.lookup(&item_name) {
+        Lookup::Found(name) => return Ok(Some(name.to_string())),
+        // Nothing close, the caller reports it as not found
+        Lookup::Suggestions(suggestions) if suggestions.is_empty() => return Ok(Some(item_name)),
+        Lookup::Suggestions(suggestions) => suggestions,
+    };
+
+    let suggest_id = format!("{}-suggest-", ctx.id());
+    let buttons = suggestions
+        .iter()
+        .enumerate()
+        .map(|(i, name)| serenity::CreateButton::new(format!("{}{}", suggest_id, i))
+            .style(serenity::ButtonStyle::Secondary)
+            .label(name.chars().take(LABEL_LIMIT).collect::<String>()))
+        .collect();
+
+    let embed = serenity::CreateEmbed::default()
+        .title(":x:  Item could not be found")
+        .description(format!("No item is called **{}**, did you mean one of these?", item_name))
+        .color(serenity::Color::RED)
+        .to_owned();
+
+    let handle = ctx.send(poise::CreateReply::default()
+        .embed(embed.clone())
+        .components(vec![serenity::CreateActionRow::Buttons(buttons)])
+    ).await?;
 
-    serenity::futures::stream::iter(hash_names)
-        .filter(move |item| serenity::futures::future::ready(smart_search(item, partial)))
-        .map(|item| item.to_string())
-        .take(15)
+    let picked = serenity::ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .filter({
+            let suggest_id = suggest_id.clone();
+            move |interaction| interaction.data.custom_id.starts_with(&suggest_id)
+        })
+        .timeout(Duration::from_secs(60))
+        .await;
+
+    let Some(interaction) = picked else {
+        handle.edit(ctx, poise::CreateReply::default().embed(embed).components(vec![])).await?;
+        return Ok(None);
+    };
+
+    let Some(name) = interaction.data.custom_id
+        .strip_prefix(&suggest_id)
+        .and_then(|i| i.parse::<usize>().ok())
+        .and_then(|i| suggestions.get(i))
+    else {
+        return Ok(None);
+    };
+
+    let message = serenity::CreateInteractionResponseMessage::new()
+        .embed(serenity::CreateEmbed::default()
+            .title(format!("Showing {}", name))
+            .color(serenity::Color::from_rgb(254, 171, 26)))
+        .components(vec![]);
+    interaction.create_response(ctx, serenity::CreateInteractionResponse::UpdateMessage(message)).await?;
+
+    Ok(Some(name.to_string()))
 }
 
 /// Check the price of a CS2 item
@@ -37,79 +107,167 @@ pub async fn price(
     ctx: Context<'_>,
     #[description = "Item name"] #[rest]
     #[autocomplete = "autocomplete_item"]
-    item_name: String
+    item_name: String,
+    #[description = "Paint seed, for rare pattern premiums"]
+    pattern: Option<u32>,
 ) -> Result<(), Error> {
+    let store = ctx.data().items().await?;
+
+    // Typos and abbreviations are resolved before locking the database, picking a suggestion can take a while
+    let Some(item_name) = resolve_item(ctx, &store, item_name).await? else {
+        return Ok(());
+    };
+
     let author_id = ctx.author().id.get() as i64;
-    let db = ctx.data().db.lock().await;
+    let db = ctx.data().db()?.lock().await;
+    let currencies = ctx.data().currencies()?;
 
-    let reply = if let Some(found_skin) = &ctx.data().item_data.get(&item_name) {
-        let author_user = db.get_user(&author_id).await?.unwrap();
+    let server_id = ctx.guild_id().map(|id| id.get()).unwrap_or(0);
+    let referral_code = if server_id == 727970463325749268 {
+        "hade"
+    } else {
+        "botchicken"
+    };
+
+    let encoded_item_name = encode(&item_name);
+    let markets_id = format!("{}-markets", ctx.id());
 
-        let rarity_color = match &found_skin.info.rarity {
-            Some(rarity) => {
-                match &rarity.color {
-                    Some(rarity_color) => serenity::Color::from_rgb(
-                        u8::from_str_radix(&rarity_color[1..3], 16).unwrap(),
-                        u8::from_str_radix(&rarity_color[3..5], 16).unwrap(),
-                        u8::from_str_radix(&rarity_color[5..7], 16).unwrap()
-                    ),
+    let components = |showing: bool| vec![serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new_link(
+            format!("https://skinport.com/market?r={}&search={}", referral_code, encoded_item_name)
+        )
+        .label("Purchase Item"),
+        serenity::CreateButton::new(&markets_id)
+            .style(serenity::ButtonStyle::Secondary)
+            .label(if showing { "Hide markets" } else { "Show all markets" }),
+    ])];
 
-                    None => serenity::Color::LIGHT_GREY,
-                }
+    // Collapsed and expanded embeds, swapped by the markets button
+    let mut market_embeds = None;
 
-            },
+    let reply = if let Some(found_skin) = store.item_data.get(item_name.as_str()) {
+        let author_user = db.get_user(&author_id).await?.unwrap();
+        let strategy = guild_strategy(&ctx, &db).await?;
+
+        let rarity_color = match found_skin.info.rarity_rgb() {
+            Some((r, g, b)) => serenity::Color::from_rgb(r, g, b),
             None => serenity::Color::LIGHT_GREY,
         };
 
         let mut embed = serenity::CreateEmbed::default()
-            .title(format!("{}", item_name))
+            .title(item_name.clone())
             .color(rarity_color)
             .fields(vec![
-                ("<:botchicken:740299794550882324>  ·  Suggested Price", match found_skin.feather {
-                    Some(p) => exchange(p, &author_user.currency, &ctx).await,
+                ("<:botchicken:740299794550882324>  ·  Suggested Price", match found_skin.suggested(strategy.as_ref()) {
+                    Some(p) => currencies.exchange(p, &author_user.currency),
                     None => "Error".to_string()
                 }, true),
-                ("<:steam:740300441044123669>  ·  Steam Market", match found_skin.steam {
-                    Some(p) => exchange(p, &author_user.currency, &ctx).await,
-                    None => "Error".to_string()
+                ("<:steam:740300441044123669>  ·  Steam Market", match (found_skin.steam(), found_skin.steam_trend()) {
+                    (Some(p), Some(trend)) => format!("{}\n{}", currencies.exchange(p, &author_user.currency), trend),
+                    (Some(p), None) => currencies.exchange(p, &author_user.currency),
+                    (None, _) => "Error".to_string()
                 }, true),
-                ("<:skinport:747619241250783353>  ·  Skinport", match found_skin.skinport {
-                    Some(p) => exchange(p, &author_user.currency, &ctx).await,
+                ("<:skinport:747619241250783353>  ·  Skinport", match found_skin.skinport() {
+                    Some(p) => currencies.exchange(p, &author_user.currency),
                     None => "Error".to_string()
                 }, true),
-                ("<:buff163:801522918776766526>  ·  buff.163", match found_skin.buff {
-                    Some(p) => exchange(p, &author_user.currency, &ctx).await,
+                ("<:buff163:801522918776766526>  ·  buff.163", match found_skin.buff() {
+                    Some(p) => currencies.exchange(p, &author_user.currency),
                     None => "Error".to_string()
                 }, true),
             ])
+            .field("Confidence", found_skin.confidence.to_string(), false)
+            .footer(serenity::CreateEmbedFooter::new(format!(
+                "Suggested price: {} · Prices updated {}",
+                strategy.as_ref().unwrap_or(&ctx.data().config.price_strategy),
+                format_age(store.prices_fetched_at)
+            )))
             .to_owned();
 
-        match &found_skin.info.image {
-            Some(imgurl) => {
-                embed = embed.thumbnail(imgurl);
+        if let Some(notice) = ctx.data().health.notice(&[Dataset::Prices, Dataset::Dopplers, Dataset::ExchangeRates]) {
+            embed = embed.field(":warning:  Degraded service", notice, false);
+        }
+
+        if let Some(seed) = pattern {
+            let premium = match (store.patterns.lookup(&item_name, seed), found_skin.suggested(strategy.as_ref())) {
+                (Some(premium), Some(p)) => format!(
+                    "Seed {} is a **{}**, worth about **{}** ({}× market)",
+                    seed,
+                    premium.name,
+                    currencies.exchange(p * premium.multiplier, &author_user.currency),
+                    premium.multiplier
+                ),
+                (Some(premium), None) => format!("Seed {} is a **{}** ({}× market)", seed, premium.name, premium.multiplier),
+                (None, _) => match store.patterns.fade(&item_name, seed) {
+                    Some(percentage) => format!("Seed {} is a {:.1}% fade and carries no premium, the market price applies", seed, percentage),
+                    None if store.patterns.covers(&item_name) => format!("Seed {} carries no known premium, the market price applies", seed),
+                    None => "No pattern premiums are tracked for this skin".to_string(),
+                },
+            };
+            embed = embed.field("Pattern", premium, false);
+        }
+
+        if found_skin.phase_fallback {
+            embed = embed.field(
+                ":warning:  Phase unknown",
+                "This is the base price across all phases, pick a specific phase for an accurate price",
+                false
+            );
+        }
+
+        if !found_skin.dropped.is_empty() {
+            let mut dropped_string = String::new();
+
+            for quote in found_skin.dropped.iter() {
+                dropped_string.push_str(&format!(
+                    "{} at {}\n",
+                    quote.label(),
+                    currencies.exchange(quote.price, &author_user.currency)
+                ));
             }
-            None => {}
+            embed = embed.field("Ignored outlier quotes", dropped_string, false);
         }
 
-        let server_id = ctx.guild_id().map(|id| id.get()).unwrap_or(0);
-        let referral_code = if server_id == 727970463325749268 {
-            "hade"
-        } else {
-            "botchicken"
-        };
+        if let Some(imgurl) = &found_skin.info.image {
+            embed = embed.thumbnail(imgurl);
+        }
+
+        // Lowest and highest are marked among the quotes that made it into the suggested price
+        let markets = found_skin.markets();
+        let lowest = markets.iter().find(|(_, ignored)| !ignored).map(|(quote, _)| quote.name());
+        let highest = markets.iter().rev().find(|(_, ignored)| !ignored).map(|(quote, _)| quote.name());
 
-        let encoded_item_name = encode(&item_name);
+        let mut markets_string = String::new();
 
-        let components = vec![serenity::CreateActionRow::Buttons(vec![
-            serenity::CreateButton::new_link(
-                format!("https://skinport.com/market?r={}&search={}", referral_code, encoded_item_name)
-            )
-            .label("Purchase Item")
-        ])];
+        for (quote, ignored) in &markets {
+            let marker = if *ignored {
+                " *(ignored outlier)*"
+            } else if Some(quote.name()) == lowest {
+                "  :arrow_down: lowest"
+            } else if Some(quote.name()) == highest {
+                "  :arrow_up: highest"
+            } else {
+                ""
+            };
+
+            markets_string.push_str(&format!(
+                "{} · **{}**{}\n",
+                quote.label(),
+                currencies.exchange(quote.price, &author_user.currency),
+                marker
+            ));
+        }
+
+        if markets_string.is_empty() {
+            markets_string = "No market currently lists this item".to_string();
+        }
+
+        let expanded = embed.clone().field("All markets", markets_string, false);
+        market_embeds = Some((embed.clone(), expanded));
 
         poise::CreateReply::default()
             .embed(embed)
-            .components(components)
+            .components(components(false))
     } else {
         let embed = serenity::CreateEmbed::default()
             .title(":x:  Item could not be found")
@@ -120,6 +278,31 @@ pub async fn price(
             .embed(embed)
     };
 
+    // Don't hold the database while waiting on button presses
+    drop(db);
+
     ctx.send(reply).await?;
+
+    if let Some((collapsed, expanded)) = market_embeds {
+        let mut showing = false;
+
+        while let Some(interaction) = serenity::ComponentInteractionCollector::new(ctx)
+            .filter({
+                let markets_id = markets_id.clone();
+                move |interaction| interaction.data.custom_id == markets_id
+            })
+            .timeout(Duration::from_secs(300))
+            .await
+        {
+            showing = !showing;
+
+            let message = serenity::CreateInteractionResponseMessage::new()
+                .embed(if showing { expanded.clone() } else { collapsed.clone() })
+                .components(components(showing));
+
+            interaction.create_response(ctx, serenity::CreateInteractionResponse::UpdateMessage(message)).await?;
+        }
+    }
+
     Ok(())
 }
@@ -0,0 +1,195 @@
+use std::io::Cursor;
+
+use image::{ImageOutputFormat, RgbImage};
+use plotters::prelude::*;
+use poise::serenity_prelude as serenity;
+use poise::ChoiceParameter;
+
+use crate::{Context, Error};
+use crate::cache::format_age;
+use crate::database::models::PricePoint;
+use crate::history::today;
+use crate::price::autocomplete_item;
+
+const CHART_WIDTH: u32 = 800;
+const CHART_HEIGHT: u32 = 400;
+
+const BACKGROUND: RGBColor = RGBColor(47, 49, 54);
+const GRID: RGBColor = RGBColor(79, 84, 92);
+
+/// Series drawn on the chart, with the legend square used in the embed
+const SERIES: [(&str, &str, RGBColor); 4] = [
+    ("Suggested", ":orange_square:", RGBColor(254, 171, 26)),
+    ("Steam", ":blue_square:", RGBColor(102, 192, 244)),
+    ("Skinport", ":green_square:", RGBColor(87, 242, 135)),
+    ("buff.163", ":purple_square:", RGBColor(181, 131, 255)),
+];
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum HistoryRange {
+    #[name = "7 days"]
+    Week,
+    #[name = "30 days"]
+    Month,
+    #[name = "90 days"]
+    Quarter,
+    #[name = "All time"]
+    All,
+}
+
+impl HistoryRange {
+    fn days(self) -> Option<i64> {
+        match self {
+            HistoryRange::Week => Some(7),
+            HistoryRange::Month => Some(30),
+            HistoryRange::Quarter => Some(90),
+            HistoryRange::All => None,
+        }
+    }
+}
+
+fn series_value(point: &PricePoint, index: usize) -> Option<f64> {
+    match index {
+        0 => point.feather,
+        1 => point.steam,
+        2 => point.skinport,
+        _ => point.buff,
+    }
+}
+
+/// Draws the history as a PNG, axis values are listed in the embed instead since no fonts are bundled
+fn render_chart(points: &[PricePoint]) -> Result<Vec<u8>, Error> {
+    let mut buffer = vec![0u8; (CHART_WIDTH * CHART_HEIGHT * 3) as usize];
+
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (CHART_WIDTH, CHART_HEIGHT)).into_drawing_area();
+        root.fill(&BACKGROUND).map_err(|e| e.to_string())?;
+
+        let first_day = points.first().map(|point| point.day).unwrap_or(0);
+        let last_day = points.last().map(|point| point.day).unwrap_or(0).max(first_day + 1);
+
+        let values: Vec<f64> = points
+            .iter()
+            .flat_map(|point| (0..SERIES.len()).filter_map(move |i| series_value(point, i)))
+            .collect();
+        let low = values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let high = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let margin = ((high - low) * 0.05).max(0.01);
+
+        let mut chart = ChartBuilder::on(&root)
+            .margin(16)
+            .build_cartesian_2d(first_day as f64..last_day as f64, (low - margin)..(high + margin))
+            .map_err(|e| e.to_string())?;
+
+        chart.configure_mesh()
+            .disable_x_axis()
+            .disable_y_axis()
+            .disable_x_mesh()
+            .y_labels(6)
+            .light_line_style(BACKGROUND)
+            .bold_line_style(GRID)
+            .draw()
+            .map_err(|e| e.to_string())?;
+
+        // Drawn back to front so the suggested price stays on top
+        for (index, (_, _, color)) in SERIES.iter().enumerate().rev() {
+            let line: Vec<(f64, f64)> = points
+                .iter()
+                .filter_map(|point| series_value(point, index).map(|value| (point.day as f64, value)))
+                .collect();
+
+            chart.draw_series(LineSeries::new(line, color.stroke_width(3)))
+                .map_err(|e| e.to_string())?;
+        }
+
+        root.present().map_err(|e| e.to_string())?;
+    }
+
+    let image = RgbImage::from_raw(CHART_WIDTH, CHART_HEIGHT, buffer).ok_or("Chart buffer has the wrong size")?;
+    let mut png = Cursor::new(Vec::new());
+    image.write_to(&mut png, ImageOutputFormat::Png)?;
+
+    Ok(png.into_inner())
+}
+
+/// Chart the price history of a CS2 item
+#[poise::command(
+    slash_command,
+    category = "Items",
+)]
+pub async fn pricehistory(
+    ctx: Context<'_>,
+    #[description = "Item name"]
+    #[autocomplete = "autocomplete_item"]
+    item_name: String,
+    #[description = "Time range, defaults to 30 days"]
+    range: Option<HistoryRange>,
+) -> Result<(), Error> {
+    let range = range.unwrap_or(HistoryRange::Month);
+    let author_id = ctx.author().id.get() as i64;
+    let db = ctx.data().db()?.lock().await;
+    let currencies = ctx.data().currencies()?;
+    let prices_fetched_at = ctx.data().items().await?.prices_fetched_at;
+
+    let author_user = db.get_user(&author_id).await?.unwrap();
+    let since_day = range.days().map(|days| today() - days).unwrap_or(0);
+    let points = db.get_price_history(&item_name, since_day).await?;
+
+    let reply = match (points.first(), points.last()) {
+        (Some(first), Some(latest)) => {
+            let chart = render_chart(&points)?;
+
+            let feather_values: Vec<f64> = points.iter().filter_map(|point| point.feather).collect();
+            let low = feather_values.iter().cloned().fold(f64::INFINITY, f64::min);
+            let high = feather_values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+            let change = match (first.feather, latest.feather) {
+                (Some(start), Some(end)) if start > 0.0 => format!("{:+.1}%", (end - start) / start * 100.0),
+                _ => "Unknown".to_string(),
+            };
+
+            let legend = SERIES
+                .iter()
+                .map(|(name, square, _)| format!("{} {}", square, name))
+                .collect::<Vec<String>>()
+                .join("  ");
+
+            let embed = serenity::CreateEmbed::default()
+                .title(format!("{} · {}", item_name, range.name()))
+                .description(legend)
+                .color(serenity::Color::from_rgb(254, 171, 26))
+                .fields(vec![
+                    ("Latest", match latest.feather {
+                        Some(p) => currencies.exchange(p, &author_user.currency),
+                        None => "Error".to_string()
+                    }, true),
+                    ("Low", currencies.exchange(low, &author_user.currency), true),
+                    ("High", currencies.exchange(high, &author_user.currency), true),
+                    ("Change", change, true),
+                ])
+                .image("attachment://history.png")
+                .footer(serenity::CreateEmbedFooter::new(format!(
+                    "{} daily snapshots · Chart in USD · Prices updated {}",
+                    points.len(),
+                    format_age(prices_fetched_at)
+                )))
+                .to_owned();
+
+            poise::CreateReply::default()
+                .embed(embed)
+                .attachment(serenity::CreateAttachment::bytes(chart, "history.png"))
+        },
+        _ => {
+            let embed = serenity::CreateEmbed::default()
+                .title(":x:  No price history recorded for this item")
+                .color(serenity::Color::RED)
+                .to_owned();
+
+            poise::CreateReply::default()
+                .embed(embed)
+        }
+    };
+
+    ctx.send(reply).await?;
+    Ok(())
+}
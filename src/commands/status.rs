@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::cache::{fetched_at, format_age};
+use crate::health::{Dataset, Status};
+use crate::items::LOCAL_FILE;
+use crate::{Context, Error};
+use poise::serenity_prelude as serenity;
+
+fn format_uptime(uptime: Duration) -> String {
+    let minutes = uptime.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / (60 * 24), minutes / 60 % 24, minutes % 60);
+
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h {}m", days, hours, minutes),
+    }
+}
+
+/// Show the health of the bot and its data
+#[poise::command(
+    slash_command,
+    category = "Utility")
+]
+pub async fn status(ctx: Context<'_>) -> Result<(), Error> {
+    let data = ctx.data();
+    let health = &data.health;
+    let store = data.items().await.ok();
+
+    let mut fields: Vec<(String, String, bool)> = vec![];
+
+    for dataset in Dataset::ALL {
+        // Prices and phases come with the item store and share its loader until it is ready
+        let status = match (dataset, &store) {
+            (Dataset::Prices | Dataset::Dopplers, None) => health.get(Dataset::Items),
+            _ => health.get(dataset),
+        };
+
+        let details = match dataset {
+            Dataset::Items => store.as_ref().map(|store| format!(
+                "{} items · updated {}",
+                store.item_data.len(),
+                format_age(fetched_at(&data.config.data_path(LOCAL_FILE)))
+            )),
+            Dataset::Prices => store.as_ref().map(|store| format!(
+                "{} priced · updated {}",
+                store.item_data.values().filter(|priced| priced.feather.is_some()).count(),
+                format_age(store.prices_fetched_at)
+            )),
+            Dataset::Dopplers => store.as_ref().map(|store| format!("{} phase icons", store.doppler_data.len())),
+            Dataset::ExchangeRates => data.currencies.get().map(|currencies| format!(
+                "{} currencies · updated {}",
+                currencies.rates.len(),
+                format_age(currencies.fetched_at)
+            )),
+            Dataset::Database => data.db.get().map(|_| "Connected".to_string()),
+        };
+
+        let value = match (details, &status) {
+            (Some(details), Status::Ready) => details,
+            (Some(details), status) => format!("{}\n{}", details, status),
+            (None, status) => status.to_string(),
+        };
+
+        fields.push((format!("{}  {}", status.emoji(), dataset.label()), value, false));
+    }
+
+    if let Some(store) = &store {
+        let mut coverage: HashMap<&str, usize> = HashMap::new();
+        for quote in store.item_data.values().flat_map(|priced| priced.quotes.iter().chain(priced.dropped.iter())) {
+            *coverage.entry(quote.label()).or_default() += 1;
+        }
+
+        let mut coverage: Vec<(&str, usize)> = coverage.into_iter().collect();
+        coverage.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+
+        let coverage_string = coverage
+            .iter()
+            .map(|(label, count)| format!("{} · **{}**", label, count))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        if !coverage_string.is_empty() {
+            fields.push(("Market coverage".to_string(), coverage_string, false));
+        }
+    }
+
+    let latency = ctx.ping().await;
+    fields.push(("Shard latency".to_string(), if latency.is_zero() {
+        "Unknown".to_string()
+    } else {
+        format!("{}ms", latency.as_millis())
+    }, true));
+    fields.push(("Uptime".to_string(), format_uptime(health.started_at.elapsed()), true));
+    fields.push(("Version".to_string(), env!("CARGO_PKG_VERSION").to_string(), true));
+
+    let healthy = Dataset::ALL.iter().all(|&dataset| health.get(dataset) == Status::Ready);
+
+    let embed = serenity::CreateEmbed::default()
+        .title(if healthy { "All systems operational" } else { "Some systems are degraded" })
+        .color(if healthy { serenity::Color::from_rgb(0, 255, 0) } else { serenity::Color::from_rgb(254, 171, 26) })
+        .fields(fields)
+        .footer(serenity::CreateEmbedFooter::new(format!("Shard #{}", ctx.serenity_context().shard_id)))
+        .to_owned();
+
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{Context, Error};
+use crate::cache::format_age;
+use crate::guild::guild_strategy;
+use crate::health::Dataset;
+use crate::price::autocomplete_item;
+use crate::contracts::Tradeup;
+
+/// Number of contracts listed by the search
+const SEARCH_RESULTS: usize = 5;
+
+/// Discord rejects embed fields longer than this
+const FIELD_LIMIT: usize = 1024;
+
+/// Joins lines until the field limit, noting how many were left out
+fn field_lines(lines: &[String]) -> String {
+    let mut field = String::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let more = format!("and {} more", lines.len() - i);
+        if field.len() + line.len() + more.len() + 2 > FIELD_LIMIT {
+            field.push_str(&more);
+            break;
+        }
+        field.push_str(line);
+        field.push('\n');
+    }
+
+    field
+}
+
+/// Trade-up contract calculator
+#[poise::command(
+    slash_command,
+    category = "Items",
+    subcommands("calculate", "search")
+)]
+pub async fn tradeup(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Outcomes and expected value of a trade-up contract
+// One option per input skin, Discord has no list options
+#[allow(clippy::too_many_arguments)]
+#[poise::command(
+    slash_command,
+    category = "Items",
+)]
+pub async fn calculate(
+    ctx: Context<'_>,
+    #[description = "Input skin 1"] #[autocomplete = "autocomplete_item"] input1: String,
+    #[description = "Input skin 2"] #[autocomplete = "autocomplete_item"] input2: String,
+    #[description = "Input skin 3"] #[autocomplete = "autocomplete_item"] input3: String,
+    #[description = "Input skin 4"] #[autocomplete = "autocomplete_item"] input4: String,
+    #[description = "Input skin 5"] #[autocomplete = "autocomplete_item"] input5: String,
+    #[description = "Input skin 6"] #[autocomplete = "autocomplete_item"] input6: String,
+    #[description = "Input skin 7"] #[autocomplete = "autocomplete_item"] input7: String,
+    #[description = "Input skin 8"] #[autocomplete = "autocomplete_item"] input8: String,
+    #[description = "Input skin 9"] #[autocomplete = "autocomplete_item"] input9: String,
+    #[description = "Input skin 10"] #[autocomplete = "autocomplete_item"] input10: String,
+) -> Result<(), Error> {
+    let inputs = vec![input1, input2, input3, input4, input5, input6, input7, input8, input9, input10];
+
+    let author_id = ctx.author().id.get() as i64;
+    let db = ctx.data().db()?.lock().await;
+    let store = ctx.data().items().await?;
+    let currencies = ctx.data().currencies()?;
+
+    let author_user = db.get_user(&author_id).await?.unwrap();
+    let strategy = guild_strategy(&ctx, &db).await?;
+    let currency = &author_user.currency;
+
+    let embed = match store.tradeups.evaluate(&inputs, &store, strategy.as_ref()) {
+        Ok(tradeup) => {
+            let mut input_counts: BTreeMap<&str, usize> = BTreeMap::new();
+            for input in &tradeup.inputs {
+                *input_counts.entry(input).or_default() += 1;
+            }
+            let input_lines: Vec<String> = input_counts
+                .iter()
+                .map(|(name, count)| format!("{}× {}", count, name))
+                .collect();
+
+            let outcome_lines: Vec<String> = tradeup.outcomes
+                .iter()
+                .map(|outcome| format!(
+                    "{} · **{}** · {:.1}% · float {:.3} – {:.3}",
+                    outcome.name,
+                    match outcome.price {
+                        Some(price) => currencies.exchange(price, currency),
+                        None => "unpriced".to_string(),
+                    },
+                    outcome.chance * 100.0,
+                    outcome.float_range.0,
+                    outcome.float_range.1
+                ))
+                .collect();
+
+            let mut embed = serenity::CreateEmbed::default()
+                .title(if tradeup.stattrak { "StatTrak™ trade-up contract" } else { "Trade-up contract" })
+                .color(serenity::Color::from_rgb(254, 171, 26))
+                .field("Inputs", field_lines(&input_lines), false)
+                .field("Cost", currencies.exchange(tradeup.cost, currency), true)
+                .field("Expected value", currencies.exchange(tradeup.expected, currency), true)
+                .field("ROI", format!("{:+.1}%", tradeup.roi() * 100.0), true)
+                .field("Profit chance", format!("{:.1}%", tradeup.profit_chance() * 100.0), true)
+                .field("Outcomes", field_lines(&outcome_lines), false)
+                .footer(serenity::CreateEmbedFooter::new(format!(
+                    "Outcome exteriors are estimated from the middle of the float range · Prices updated {}",
+                    format_age(store.prices_fetched_at)
+                )))
+                .to_owned();
+
+            if let Some(notice) = ctx.data().health.notice(&[Dataset::Prices, Dataset::ExchangeRates]) {
+                embed = embed.field(":warning:  Degraded service", notice, false);
+            }
+
+            embed
+        },
+        Err(reason) => serenity::CreateEmbed::default()
+            .title(":x:  Invalid trade-up")
+            .description(reason)
+            .color(serenity::Color::RED)
+            .to_owned(),
+    };
+
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+fn search_line(tradeup: &Tradeup, exchange: impl Fn(f64) -> String) -> String {
+    format!(
+        "10× **{}**\nCost {} · EV {} · ROI **{:+.1}%** · Profit chance {:.0}%\n",
+        tradeup.inputs[0],
+        exchange(tradeup.cost),
+        exchange(tradeup.expected),
+        tradeup.roi() * 100.0,
+        tradeup.profit_chance() * 100.0
+    )
+}
+
+/// Find the most profitable trade-ups with inputs in a price band
+#[poise::command(
+    slash_command,
+    category = "Items",
+)]
+pub async fn search(
+    ctx: Context<'_>,
+    #[description = "Lowest price per input skin, in USD"] min_price: f64,
+    #[description = "Highest price per input skin, in USD"] max_price: f64,
+) -> Result<(), Error> {
+    let store = ctx.data().items().await?;
+    let currencies = ctx.data().currencies()?;
+
+    let (strategy, currency) = {
+        let db = ctx.data().db()?.lock().await;
+        let author_user = db.get_user(&(ctx.author().id.get() as i64)).await?.unwrap();
+        (guild_strategy(&ctx, &db).await?, author_user.currency)
+    };
+
+    // Scanning every skin takes a moment
+    ctx.defer().await?;
+
+    let found = store.tradeups.search(min_price.min(max_price), max_price.max(min_price), &store, strategy.as_ref(), SEARCH_RESULTS);
+
+    let embed = if found.is_empty() {
+        serenity::CreateEmbed::default()
+            .title(":x:  No trade-ups found in this price band")
+            .color(serenity::Color::RED)
+            .to_owned()
+    } else {
+        let mut results = String::new();
+        for tradeup in &found {
+            results.push_str(&search_line(tradeup, |amount| currencies.exchange(amount, &currency)));
+        }
+
+        serenity::CreateEmbed::default()
+            .title(format!("Best trade-ups with inputs between ${:.2} and ${:.2}", min_price.min(max_price), max_price.max(min_price)))
+            .description(results)
+            .color(serenity::Color::from_rgb(254, 171, 26))
+            .footer(serenity::CreateEmbedFooter::new(format!(
+                "Ten copies of one skin per contract · Use /tradeup calculate for details · Prices updated {}",
+                format_age(store.prices_fetched_at)
+            )))
+            .to_owned()
+    };
+
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
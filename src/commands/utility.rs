@@ -1,12 +1,18 @@
 use poise::serenity_prelude as serenity;
 use serenity::futures::{Stream, StreamExt};
+use crate::health::{Dataset, NotReady};
 use crate::{Context, Error};
 
 async fn autocomplete_currency<'a>(
     ctx: Context<'a>,
     partial: &'a str,
 ) -> impl Stream<Item = String> + 'a {
-    serenity::futures::stream::iter(&ctx.data().all_currency_codes)
+    let codes = ctx.data()
+        .currencies()
+        .map(|currencies| currencies.codes.as_slice())
+        .unwrap_or_default();
+
+    serenity::futures::stream::iter(codes)
         .filter(move |name| serenity::futures::future::ready(name.starts_with(&partial.to_ascii_uppercase())))
         .map(|name| name.to_string())
         .take(10)
@@ -26,14 +32,20 @@ pub async fn currency(
     let iso_currency = currency.to_ascii_uppercase();
 
     let user_id = ctx.author().id.get() as i64;
-    let db = ctx.data().db.lock().await;
+    let db = ctx.data().db()?.lock().await;
+    let currencies = ctx.data().currencies()?;
+
+    // Only USD is known until the real rates load
+    if currencies.usd_only {
+        return Err(NotReady(Dataset::ExchangeRates).into());
+    }
 
     const ICON_URL: &str = "https://cdn.discordapp.com/avatars/371822760499871756/1caf027942b849dd774030ec8b333c10.webp";
     let mut embed = serenity::CreateEmbed::default()
         .author(serenity::CreateEmbedAuthor::new("Feather Inventory Valuation").icon_url(ICON_URL)).to_owned();
 
     if let Some(mut target_user) = db.get_user(&user_id).await? {
-        if ctx.data().all_currency_codes.contains(&iso_currency) {
+        if currencies.codes.contains(&iso_currency) {
             target_user.currency = iso_currency.clone();
             db.update_user(&target_user).await?;
 
@@ -68,7 +80,7 @@ pub async fn unlink(
 ) -> Result<(), Error> {
 
     let user_id = ctx.author().id.get() as i64;
-    let db = ctx.data().db.lock().await;
+    let db = ctx.data().db()?.lock().await;
 
     const ICON_URL: &str = "https://cdn.discordapp.com/avatars/371822760499871756/1caf027942b849dd774030ec8b333c10.webp";
     let mut embed = serenity::CreateEmbed::default()
@@ -1,4 +1,15 @@
+use std::collections::HashMap;
 use std::env;
+use std::fmt::Display;
+use std::num::NonZeroU64;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::applied::AppliedValue;
+use crate::outliers::OutlierFilter;
+use crate::sources::registry;
+use crate::strategy::Strategy;
 
 #[derive(Clone, Debug)]
 pub struct Config {
@@ -6,6 +17,19 @@ pub struct Config {
     pub invite_link: String,
     pub steamweb_token: String,
     pub steam_token: String,
+    pub price_refresh_hours: u64,
+    /// Cached downloads older than this are fetched again at startup
+    pub max_data_age_hours: u64,
+    /// Directory holding the cached downloads, `doppler.json` and `patterns.json`
+    pub data_dir: PathBuf,
+    /// Only use local files, never download item, price or exchange rate data
+    pub offline: bool,
+    pub price_strategy: Strategy,
+    pub source_weights: HashMap<String, f64>,
+    pub outlier_filter: OutlierFilter,
+    /// What applied stickers and patches add to an item in inventory values
+    pub sticker_value: AppliedValue,
+    pub charm_value: AppliedValue,
 }
 
 impl Config {
@@ -15,6 +39,106 @@ impl Config {
             invite_link: env::var("INVITE_LINK")?,
             steamweb_token: env::var("STEAMWEB_TOKEN")?,
             steam_token: env::var("STEAM_TOKEN")?,
+            // A zero period would make the refresh timer panic
+            price_refresh_hours: parse_env::<NonZeroU64>("PRICE_REFRESH_HOURS")
+                .map_or(6, NonZeroU64::get),
+            max_data_age_hours: parse_env("MAX_DATA_AGE_HOURS")
+                .unwrap_or(24),
+            data_dir: env::var("DATA_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from(".")),
+            offline: env::var("OFFLINE")
+                .map(|offline| matches!(offline.to_lowercase().as_str(), "1" | "true" | "yes"))
+                .unwrap_or(false),
+            price_strategy: parse_env("PRICE_STRATEGY")
+                .unwrap_or_default(),
+            source_weights: env::var("PRICE_WEIGHTS")
+                .map(|weights| parse_weights(&weights))
+                .unwrap_or_default(),
+            outlier_filter: parse_env("OUTLIER_FILTER")
+                .unwrap_or_default(),
+            sticker_value: parse_env("STICKER_VALUE")
+                .unwrap_or_else(AppliedValue::stickers),
+            charm_value: parse_env("CHARM_VALUE")
+                .unwrap_or_else(AppliedValue::charms),
         })
     }
-}
\ No newline at end of file
+
+    /// Path of a data file inside the data directory
+    pub fn data_path(&self, file: &str) -> String {
+        self.data_dir.join(file).to_string_lossy().into_owned()
+    }
+
+    pub fn max_data_age(&self) -> Duration {
+        Duration::from_secs(self.max_data_age_hours * 60 * 60)
+    }
+}
+
+#[cfg(test)]
+impl Config {
+    /// Default settings that only read the data files in `data_dir`
+    pub fn offline(data_dir: PathBuf) -> Self {
+        Config {
+            discord_token: String::new(),
+            invite_link: String::new(),
+            steamweb_token: String::new(),
+            steam_token: String::new(),
+            price_refresh_hours: 6,
+            max_data_age_hours: 24,
+            data_dir,
+            offline: true,
+            price_strategy: Strategy::default(),
+            source_weights: HashMap::new(),
+            outlier_filter: OutlierFilter::default(),
+            sticker_value: AppliedValue::stickers(),
+            charm_value: AppliedValue::charms(),
+        }
+    }
+}
+
+/// Parses an optional setting, reporting a malformed value at startup rather than quietly using the default
+fn parse_env<T: FromStr>(name: &str) -> Option<T>
+where
+    T::Err: Display,
+{
+    let value = env::var(name).ok()?;
+
+    match value.parse() {
+        Ok(parsed) => Some(parsed),
+        Err(e) => {
+            eprintln!("Invalid {} `{}`: {}, using the default", name, value, e);
+            None
+        },
+    }
+}
+
+/// Parses `market:weight` pairs such as `steam:2,lootfarm:0.5`, reporting and skipping malformed ones
+fn parse_weights(weights: &str) -> HashMap<String, f64> {
+    let mut parsed = HashMap::new();
+
+    for pair in weights.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+        let weight = pair.split_once(':').and_then(|(market, weight)| {
+            let weight = weight.trim().parse::<f64>().ok().filter(|weight| weight.is_finite() && *weight >= 0.0)?;
+            Some((market.trim(), weight))
+        });
+
+        match weight {
+            Some((market, weight)) if registry().iter().any(|source| source.name() == market) => {
+                parsed.insert(market.to_string(), weight);
+            },
+            Some((market, _)) => eprintln!("Unknown market `{}` in PRICE_WEIGHTS, ignoring it", market),
+            None => eprintln!("Invalid PRICE_WEIGHTS pair `{}`, expected market:weight with a weight of at least 0", pair),
+        }
+    }
+
+    // With nothing left to weigh, every weighted price would come out empty
+    let all_zero = registry()
+        .iter()
+        .all(|source| parsed.get(source.name()).copied().unwrap_or(source.weight()) == 0.0);
+    if all_zero {
+        eprintln!("PRICE_WEIGHTS gives every market a weight of 0, using the default weights");
+        return HashMap::new();
+    }
+
+    parsed
+}
@@ -1,19 +1,23 @@
 use std::env;
 use std::sync::Arc;
 
-use mongodb::{Client, Collection};
+use mongodb::{Client, Collection, IndexModel};
 use mongodb::bson::{doc, to_bson};
-use mongodb::options::UpdateOptions;
+use mongodb::options::{FindOptions, IndexOptions, InsertManyOptions, UpdateOptions};
+use serenity::futures::TryStreamExt;
 
 use tokio::sync::Mutex;
 
-use super::models::{User, Guild};
+use poise::serenity_prelude as serenity;
+
+use super::models::{User, Guild, PricePoint};
 
 pub struct DatabaseManager {
     // client: Client,
     // db: Database,
     users: Collection<User>,
     guilds: Collection<Guild>,
+    price_history: Collection<PricePoint>,
 }
 
 impl DatabaseManager {
@@ -23,6 +27,19 @@ impl DatabaseManager {
         let db = client.database("botchicken");
         let users = db.collection("users");
         let guilds = db.collection("guilds");
+        let price_history: Collection<PricePoint> = db.collection("price_history");
+
+        let history_index = IndexModel::builder()
+            .keys(doc! { "name": 1, "day": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        price_history.create_index(history_index, None).await?;
+
+        // Counting a day's snapshot would otherwise scan the whole collection
+        let day_index = IndexModel::builder()
+            .keys(doc! { "day": 1 })
+            .build();
+        price_history.create_index(day_index, None).await?;
 
         println!("Database successfully connected");
 
@@ -31,6 +48,7 @@ impl DatabaseManager {
             // db,
             users,
             guilds,
+            price_history,
         })))
     }
 
@@ -76,6 +94,7 @@ impl DatabaseManager {
                 let default_guild = Guild {
                     guild_id: *guild_id,
                     roles: vec![],
+                    strategy: None,
                 };
 
                 self.create_guild(default_guild.clone()).await?;
@@ -90,6 +109,7 @@ impl DatabaseManager {
         let roles_bson = to_bson(&guild.roles)?;
         let update = doc! { "$set": {
             "roles": roles_bson,
+            "strategy": &guild.strategy,
         }};
         let options = UpdateOptions::builder().upsert(true).build();
         
@@ -101,4 +121,49 @@ impl DatabaseManager {
         self.guilds.insert_one(guild, None).await?;
         Ok(())
     }
+
+    /// Handle for recording history without holding the manager lock, it shares the client's connection pool
+    pub fn price_history(&self) -> PriceHistory {
+        PriceHistory(self.price_history.clone())
+    }
+
+    pub async fn get_price_history(&self, name: &str, since_day: i64) -> mongodb::error::Result<Vec<PricePoint>> {
+        let filter = doc! { "name": name, "day": { "$gte": since_day } };
+        let options = FindOptions::builder().sort(doc! { "day": 1 }).build();
+
+        self.price_history.find(filter, options).await?.try_collect().await
+    }
+}
+
+pub struct PriceHistory(Collection<PricePoint>);
+
+impl PriceHistory {
+    /// Number of items already recorded for `day`
+    pub async fn count_snapshot(&self, day: i64) -> mongodb::error::Result<u64> {
+        self.0.count_documents(doc! { "day": day }, None).await
+    }
+
+    pub async fn insert_snapshot(&self, points: Vec<PricePoint>) -> mongodb::error::Result<()> {
+        // Unordered so a partially written day can be completed without failing on duplicates
+        let options = InsertManyOptions::builder().ordered(false).build();
+
+        match self.0.insert_many(points, options).await {
+            Err(e) if !is_duplicate_only(&e) => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+const DUPLICATE_KEY: i32 = 11000;
+
+fn is_duplicate_only(error: &mongodb::error::Error) -> bool {
+    match &*error.kind {
+        mongodb::error::ErrorKind::BulkWrite(failure) => {
+            failure.write_concern_error.is_none()
+                && failure.write_errors
+                    .as_ref()
+                    .is_some_and(|errors| errors.iter().all(|e| e.code == DUPLICATE_KEY))
+        },
+        _ => false,
+    }
 }
\ No newline at end of file
@@ -25,4 +25,18 @@ pub struct RoleAssignment {
 pub struct Guild {
     pub guild_id: i64,
     pub roles: Vec<RoleAssignment>,
-}
\ No newline at end of file
+    #[serde(default)]
+    pub strategy: Option<String>,
+}
+
+/// Daily snapshot of one item's prices
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PricePoint {
+    pub name: String,
+    /// Days since the Unix epoch
+    pub day: i64,
+    pub feather: Option<f64>,
+    pub steam: Option<f64>,
+    pub skinport: Option<f64>,
+    pub buff: Option<f64>,
+}
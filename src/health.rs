@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use poise::serenity_prelude as serenity;
+use tokio::sync::{Notify, OnceCell};
+
+use crate::{Data, Error};
+
+/// How long to wait before retrying a dataset that failed to load
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Everything the bot depends on, reported separately in `/status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dataset {
+    Items,
+    Prices,
+    Dopplers,
+    ExchangeRates,
+    Database,
+}
+
+impl Dataset {
+    pub const ALL: [Dataset; 5] = [
+        Dataset::Items,
+        Dataset::Prices,
+        Dataset::Dopplers,
+        Dataset::ExchangeRates,
+        Dataset::Database,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Dataset::Items => "Item catalog",
+            Dataset::Prices => "Prices",
+            Dataset::Dopplers => "Doppler phases",
+            Dataset::ExchangeRates => "Exchange rates",
+            Dataset::Database => "Database",
+        }
+    }
+}
+
+impl fmt::Display for Dataset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dataset::Items => write!(f, "the item catalog"),
+            Dataset::Prices => write!(f, "item prices"),
+            Dataset::Dopplers => write!(f, "Doppler phases"),
+            Dataset::ExchangeRates => write!(f, "exchange rates"),
+            Dataset::Database => write!(f, "the database connection"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    Loading,
+    Ready,
+    /// Usable, but with a caveat users should know about
+    Degraded(String),
+    /// Not usable, the reason is the last error
+    Failed(String),
+}
+
+impl Status {
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            Status::Loading => ":hourglass:",
+            Status::Ready => ":green_circle:",
+            Status::Degraded(_) => ":yellow_circle:",
+            Status::Failed(_) => ":red_circle:",
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Loading => write!(f, "Loading"),
+            Status::Ready => write!(f, "Ready"),
+            Status::Degraded(reason) => write!(f, "Degraded: {}", reason),
+            Status::Failed(reason) => write!(f, "Unavailable: {}", reason),
+        }
+    }
+}
+
+/// Current status of every dataset, shared by the loaders, the refresh task and commands
+pub struct Health {
+    pub started_at: Instant,
+    statuses: Mutex<HashMap<Dataset, Status>>,
+    /// Wakes tasks waiting in `ready` whenever a status changes
+    changed: Notify,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Health {
+            started_at: Instant::now(),
+            statuses: Mutex::new(HashMap::new()),
+            changed: Notify::new(),
+        }
+    }
+}
+
+impl Health {
+    pub fn get(&self, dataset: Dataset) -> Status {
+        self.statuses.lock()
+            .map(|statuses| statuses.get(&dataset).cloned().unwrap_or(Status::Loading))
+            .unwrap_or(Status::Loading)
+    }
+
+    pub fn set(&self, dataset: Dataset, status: Status) {
+        if let Ok(mut statuses) = self.statuses.lock() {
+            statuses.insert(dataset, status);
+        }
+        self.changed.notify_waiters();
+    }
+
+    /// Waits until `dataset` is ready, without polling
+    pub async fn ready(&self, dataset: Dataset) {
+        loop {
+            // Registered before checking, so a change in between still wakes us
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            if self.get(dataset) == Status::Ready {
+                return;
+            }
+            changed.await;
+        }
+    }
+
+    /// One line per dataset among `datasets` that is degraded or unavailable, for a warning field
+    pub fn notice(&self, datasets: &[Dataset]) -> Option<String> {
+        let lines: Vec<String> = datasets
+            .iter()
+            .filter_map(|&dataset| match self.get(dataset) {
+                Status::Failed(_) if dataset == Dataset::ExchangeRates => {
+                    Some(format!("**{}** · Unavailable, prices are shown in USD", dataset.label()))
+                },
+                status @ (Status::Degraded(_) | Status::Failed(_)) => {
+                    Some(format!("**{}** · {}", dataset.label(), status))
+                },
+                _ => None,
+            })
+            .collect();
+
+        if lines.is_empty() {
+            None
+        } else {
+            Some(lines.join("\n"))
+        }
+    }
+}
+
+/// Returned by the `Data` accessors while a dataset is loading or failed to load
+#[derive(Debug)]
+pub struct NotReady(pub Dataset);
+
+impl fmt::Display for NotReady {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} not ready", self.0)
+    }
+}
+
+impl std::error::Error for NotReady {}
+
+/// Loads a dataset into `cell` in the background, retrying until it succeeds.
+/// `load` may set the status of related datasets itself, `dataset` is marked ready once it returns.
+pub fn spawn_loader<T, F, Fut>(dataset: Dataset, cell: Arc<OnceCell<T>>, health: Arc<Health>, load: F)
+where
+    T: Send + Sync + 'static,
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<T, Error>> + Send,
+{
+    tokio::spawn(async move {
+        loop {
+            match load().await {
+                Ok(data) => {
+                    let _ = cell.set(data);
+                    health.set(dataset, Status::Ready);
+                    println!("Finished loading {}", dataset);
+                    return;
+                },
+                Err(e) => {
+                    eprintln!("Failed to load {}, retrying in {}s: {}", dataset, RETRY_DELAY.as_secs(), e);
+                    health.set(dataset, Status::Failed(e.to_string()));
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+            }
+        }
+    });
+}
+
+/// Answers commands that need a dataset which isn't available, everything else goes to poise
+pub async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
+    if let poise::FrameworkError::Command { error, ctx, .. } = &error {
+        if let Some(NotReady(dataset)) = error.downcast_ref::<NotReady>() {
+            let embed = match ctx.data().health.get(*dataset) {
+                Status::Failed(reason) => serenity::CreateEmbed::default()
+                    .title(format!(":x:  {} unavailable", dataset.label()))
+                    .description(format!("Feather could not load {} and keeps retrying in the background.\n`{}`\nSee `/status` for details", dataset, reason))
+                    .color(serenity::Color::RED),
+                _ => serenity::CreateEmbed::default()
+                    .title(":hourglass:  Warming up")
+                    .description(format!("Feather just started and is still loading {}, try again in a minute", dataset))
+                    .color(serenity::Color::from_rgb(254, 171, 26)),
+            };
+
+            if let Err(e) = ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true)).await {
+                eprintln!("Failed to send unavailable message: {}", e);
+            }
+            return;
+        }
+    }
+
+    if let Err(e) = poise::builtins::on_error(error).await {
+        eprintln!("Error while handling error: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn ready_waits_for_the_dataset() {
+        let health = Arc::new(Health::default());
+        let waiter = tokio::spawn({
+            let health = health.clone();
+            async move { health.ready(Dataset::Database).await }
+        });
+
+        health.set(Dataset::Database, Status::Failed("connection refused".to_string()));
+        health.set(Dataset::Items, Status::Ready);
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+
+        health.set(Dataset::Database, Status::Ready);
+        tokio::time::timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap();
+    }
+}
@@ -1,32 +1,55 @@
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
 
-use currency::load_exchange_rates;
+use currency::{load_exchange_rates, Currencies};
 // External crates
 use poise::serenity_prelude as serenity;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, OnceCell, RwLock};
 
 // Local module imports
 mod commands;
 mod config;
 mod database;
+mod health;
 mod pricing;
 
 // Re-exports from local
 use commands::*;
 use config::Config;
 use database::DatabaseManager;
+use health::{spawn_loader, Dataset, Health, NotReady, Status};
 use pricing::*;
-use priced_items::{consolidate_prices, Priced};
+use store::{load_store, report_health, spawn_refresh_task, ItemStore, SharedStore};
 
+/// Datasets are filled in by background loaders, use the accessors to get at them
 struct Data {
     config: Config,
-    item_data: HashMap<String, Priced>,
-    doppler_data: HashMap<String, String>,
-    all_hash_names: Vec<String>,
-    all_currency_codes: Vec<String>,
-    currency_formats: HashMap<String, String>,
-    conversion_rates: HashMap<String, f64>,
-    db: Arc<Mutex<DatabaseManager>>,
+    store: Arc<OnceCell<SharedStore>>,
+    currencies: Arc<OnceCell<Currencies>>,
+    db: Arc<OnceCell<Arc<Mutex<DatabaseManager>>>>,
+    health: Arc<Health>,
+    /// Served instead of the exchange rates when they could not be loaded
+    usd_only: Currencies,
+}
+
+impl Data {
+    /// Current item snapshot
+    async fn items(&self) -> Result<Arc<ItemStore>, Error> {
+        let store = self.store.get().ok_or(NotReady(Dataset::Items))?;
+        Ok(store.read().await.clone())
+    }
+
+    /// Exchange rates, or USD only once loading them has failed
+    fn currencies(&self) -> Result<&Currencies, Error> {
+        match self.currencies.get() {
+            Some(currencies) => Ok(currencies),
+            None if matches!(self.health.get(Dataset::ExchangeRates), Status::Failed(_)) => Ok(&self.usd_only),
+            None => Err(NotReady(Dataset::ExchangeRates).into()),
+        }
+    }
+
+    fn db(&self) -> Result<&Arc<Mutex<DatabaseManager>>, Error> {
+        Ok(self.db.get().ok_or(NotReady(Dataset::Database))?)
+    }
 }
 
 type Error = Box<dyn std::error::Error + Send + Sync>;
@@ -48,35 +71,42 @@ async fn main() {
     let config = Config::load_env().expect("Failed to load config");
     let token = config.discord_token.clone();
 
-    // Load item information, but don't crash if fail
-    let (item_data, doppler_data) = consolidate_prices().await.unwrap_or_else(|e| {
-        eprintln!("Failed to pull items: {}", e);
-        (HashMap::new(), HashMap::new())
-    });
-
-    let mut all_hash_names: Vec<String> = item_data.keys().cloned().collect();
-    all_hash_names.sort_by(|a, b| {
-        let count_a = a.split_whitespace().count();
-        let count_b = b.split_whitespace().count();
+    // Datasets load concurrently while the bot connects, commands answer with a loading message until then
+    let store: Arc<OnceCell<SharedStore>> = Arc::new(OnceCell::new());
+    let currencies: Arc<OnceCell<Currencies>> = Arc::new(OnceCell::new());
+    let db: Arc<OnceCell<Arc<Mutex<DatabaseManager>>>> = Arc::new(OnceCell::new());
+    let health = Arc::new(Health::default());
 
-        let word_count_cmp = count_a.cmp(&count_b);
-        if word_count_cmp == std::cmp::Ordering::Equal {
-            a.cmp(b)
-        } else {
-            word_count_cmp
-        }
+    spawn_loader(Dataset::Database, db.clone(), health.clone(), || async {
+        Ok(DatabaseManager::new().await?)
     });
 
-    // Currency information
-    let (currency_data, currency_formats) = load_exchange_rates().await.expect("Failed to load currencies");
-
-    let mut all_currency_codes: Vec<String> = currency_formats.keys().cloned().collect();
-    all_currency_codes.sort();
-
-    let conversion_rates = currency_data.conversion_rates;
+    {
+        let config = config.clone();
+        spawn_loader(Dataset::ExchangeRates, currencies.clone(), health.clone(), move || {
+            let config = config.clone();
+            async move { load_exchange_rates(&config).await }
+        });
+    }
 
-    // Load database manager, crash if fail
-    let db = DatabaseManager::new().await.expect("Database failed to connect");
+    {
+        let config = config.clone();
+        let db = db.clone();
+        let loader_health = health.clone();
+        spawn_loader(Dataset::Items, store.clone(), health.clone(), move || {
+            let config = config.clone();
+            let db = db.clone();
+            let health = loader_health.clone();
+            async move {
+                let item_store = load_store(&config).await?;
+                report_health(&item_store, &config, &health);
+
+                let store: SharedStore = Arc::new(RwLock::new(Arc::new(item_store)));
+                spawn_refresh_task(store.clone(), config, db, health);
+                Ok(store)
+            }
+        });
+    }
 
     // Register Discord gateway intents
     let intents = serenity::GatewayIntents::GUILD_MESSAGES
@@ -88,6 +118,10 @@ async fn main() {
             commands: vec![
                 help::help(),
                 price::price(),
+                item::item(),
+                case::case(),
+                tradeup::tradeup(),
+                pricehistory::pricehistory(),
 
                 inventory::inv(),
 
@@ -95,28 +129,29 @@ async fn main() {
                 guild::list(),
                 guild::add(),
                 guild::remove(),
+                guild::pricestrategy(),
 
                 utility::currency(),
                 utility::unlink(),
+                status::status(),
             ],
+            on_error: |error| Box::pin(health::on_error(error)),
             prefix_options: poise::PrefixFrameworkOptions {
                 prefix: Some("-".into()),
                 ..Default::default()
             },
             ..Default::default()
         })
-        .setup(|ctx, _ready, framework| {
+        .setup(move |ctx, _ready, framework| {
             Box::pin(async move {
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
-                Ok(Data { 
+                Ok(Data {
                     config,
-                    item_data,
-                    doppler_data,
-                    all_hash_names,
-                    all_currency_codes,
-                    currency_formats,
-                    conversion_rates,
+                    store,
+                    currencies,
                     db,
+                    health,
+                    usd_only: Currencies::usd_only(),
                 })
             })
         })
@@ -0,0 +1,154 @@
+use std::str::FromStr;
+
+/// Share of a sticker's or charm's market price it adds once applied to an item.
+/// Pricier stickers, such as old tournament ones, keep more of their value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppliedValue {
+    base: f64,
+    /// `(min_price, share)` pairs, cheapest first
+    tiers: Vec<(f64, f64)>,
+}
+
+impl AppliedValue {
+    pub fn stickers() -> Self {
+        AppliedValue {
+            base: 0.1,
+            tiers: vec![(100.0, 0.2), (1000.0, 0.35)],
+        }
+    }
+
+    pub fn charms() -> Self {
+        AppliedValue {
+            base: 0.5,
+            tiers: vec![],
+        }
+    }
+
+    /// What an applied copy of something priced `price` adds
+    pub fn apply(&self, price: f64) -> f64 {
+        let share = self.tiers
+            .iter()
+            .rev()
+            .find(|(min_price, _)| price >= *min_price)
+            .map(|(_, share)| *share)
+            .unwrap_or(self.base);
+
+        price * share
+    }
+}
+
+impl FromStr for AppliedValue {
+    type Err = String;
+
+    /// Parses a base share followed by `min_price:share` tiers, e.g. `0.1,100:0.2,1000:0.35`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',').map(str::trim);
+
+        let parse_share = |share: &str| share.parse::<f64>()
+            .ok()
+            .filter(|share| (0.0..=1.0).contains(share))
+            .ok_or(format!("Invalid share `{}`, expected a number between 0 and 1", share));
+
+        let base = parse_share(parts.next().unwrap_or_default())?;
+
+        let mut tiers = vec![];
+        for tier in parts {
+            let (min_price, share) = tier.split_once(':').ok_or(format!("Invalid tier `{}`", tier))?;
+            let min_price = min_price.trim().parse::<f64>()
+                .ok()
+                .filter(|min_price| min_price.is_finite())
+                .ok_or(format!("Invalid tier price `{}`", min_price))?;
+            tiers.push((min_price, parse_share(share.trim())?));
+        }
+        tiers.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        Ok(AppliedValue { base, tiers })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AppliedKind {
+    Sticker,
+    Charm,
+}
+
+/// Stickers, patches and charms listed in one line of a Steam asset description, such as
+/// `<div id="sticker_info" ...><img ...><br>Sticker: Titan | Katowice 2014, Virtus.Pro | Katowice 2014</div>`.
+/// Returns their market hash names.
+pub fn parse_applied(html: &str) -> Vec<(AppliedKind, String)> {
+    let kind = if html.contains("sticker_info") {
+        AppliedKind::Sticker
+    } else if html.contains("keychain_info") {
+        AppliedKind::Charm
+    } else {
+        return vec![];
+    };
+
+    // The names follow the images, after the last line break
+    let text = html.rsplit("<br>").next().unwrap_or_default();
+    let text = text.split('<').next().unwrap_or_default();
+
+    let Some((label, names)) = text.split_once(": ") else {
+        return vec![];
+    };
+
+    names
+        .split(", ")
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| (kind, format!("{} | {}", label.trim(), name)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STICKERS: &str = r#"<br><div id="sticker_info" name="sticker_info" title="Sticker" style="border: 2px solid rgb(102, 102, 102); border-radius: 6px; width=100; margin:4px; padding:8px;"><center><img width=64 height=48 src="https://steamcdn-a.akamaihd.net/apps/730/icons/econ/stickers/kat2014/titan.png"><img width=64 height=48 src="https://steamcdn-a.akamaihd.net/apps/730/icons/econ/stickers/kat2014/ibuypower.png"><br>Sticker: Titan | Katowice 2014, iBUYPOWER | Katowice 2014</center></div>"#;
+    const PATCHES: &str = r#"<br><div id="sticker_info" name="sticker_info" title="Patch" style="border: 2px solid rgb(102, 102, 102); border-radius: 6px; width=100; margin:4px; padding:8px;"><center><img width=64 height=48 src="https://steamcdn-a.akamaihd.net/apps/730/icons/econ/patches/case01/patch_dragon.png"><br>Patch: Dragon</center></div>"#;
+    const CHARM: &str = r#"<br><div id="keychain_info" name="keychain_info" title="Charm" style="border: 2px solid rgb(102, 102, 102); border-radius: 6px; width=100; margin:4px; padding:8px;"><center><img width=64 height=48 src="https://steamcdn-a.akamaihd.net/apps/730/icons/econ/keychains/missinglink/kc_missinglink_squirt.png"><br>Charm: Lil' Squirt</center></div>"#;
+
+    #[test]
+    fn parses_stickers() {
+        assert_eq!(parse_applied(STICKERS), vec![
+            (AppliedKind::Sticker, "Sticker | Titan | Katowice 2014".to_string()),
+            (AppliedKind::Sticker, "Sticker | iBUYPOWER | Katowice 2014".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn parses_patches() {
+        assert_eq!(parse_applied(PATCHES), vec![(AppliedKind::Sticker, "Patch | Dragon".to_string())]);
+    }
+
+    #[test]
+    fn parses_charms() {
+        assert_eq!(parse_applied(CHARM), vec![(AppliedKind::Charm, "Charm | Lil' Squirt".to_string())]);
+    }
+
+    #[test]
+    fn ignores_other_lines() {
+        assert!(parse_applied("Exterior: Field-Tested").is_empty());
+        assert!(parse_applied("").is_empty());
+    }
+
+    #[test]
+    fn picks_the_highest_tier_reached() {
+        let value: AppliedValue = "0.1, 1000:0.35, 100:0.2".parse().unwrap();
+
+        assert!((value.apply(50.0) - 5.0).abs() < 1e-9);
+        assert!((value.apply(100.0) - 20.0).abs() < 1e-9);
+        assert!((value.apply(999.0) - 199.8).abs() < 1e-9);
+        assert!((value.apply(2000.0) - 700.0).abs() < 1e-9);
+        assert_eq!(value, AppliedValue::stickers());
+    }
+
+    #[test]
+    fn rejects_malformed_values() {
+        assert!("".parse::<AppliedValue>().is_err());
+        assert!("1.5".parse::<AppliedValue>().is_err());
+        assert!("0.1,100".parse::<AppliedValue>().is_err());
+        assert!("0.1,inf:0.2".parse::<AppliedValue>().is_err());
+        assert_eq!("0.5".parse::<AppliedValue>(), Ok(AppliedValue::charms()));
+    }
+}
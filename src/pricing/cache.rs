@@ -0,0 +1,363 @@
+use std::fs;
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::Error;
+
+/// Sidecar metadata stored next to each cached download
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CacheMeta {
+    /// Unix seconds of the last successful fetch
+    pub fetched_at: i64,
+    /// Validators from the last full download, sent back on the next conditional request
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub last_modified: Option<String>,
+}
+
+/// Body and validators of a full (non-304) download
+pub struct Fetched {
+    pub body: Vec<u8>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn meta_path(path: &str) -> String {
+    format!("{}.meta", path)
+}
+
+/// Last known good copy, kept when a download replaces `path`
+fn previous_path(path: &str) -> String {
+    format!("{}.prev", path)
+}
+
+/// Runs file reads, parsing and index builds on the blocking pool, keeping them off the async workers
+pub async fn blocking<T: Send + 'static>(work: impl FnOnce() -> Result<T, Error> + Send + 'static) -> Result<T, Error> {
+    tokio::task::spawn_blocking(work).await?
+}
+
+/// Reads and parses a cached file, rolling back to the previous good copy if it is unreadable
+pub fn read_json<T: DeserializeOwned>(path: &str) -> Result<T, Error> {
+    read_with_rollback(path, |bytes| Ok(serde_json::from_slice(bytes)?))
+}
+
+/// Like [`read_json`], with a custom parser for files too large to hold as a `Value` tree
+pub fn read_with_rollback<T>(path: &str, parse: impl Fn(&[u8]) -> Result<T, Error>) -> Result<T, Error> {
+    let read = |path: &str| -> Result<T, Error> {
+        parse(&fs::read(path)?)
+    };
+
+    match read(path) {
+        Ok(data) => Ok(data),
+        Err(e) => {
+            let previous = previous_path(path);
+            if fs::metadata(&previous).is_err() {
+                return Err(e);
+            }
+
+            eprintln!("Failed to read {} ({}), rolling back to {}", path, e, previous);
+            let data = read(&previous)?;
+            fs::copy(&previous, path)?;
+
+            // The validators describe the broken download, keeping them would get 304s for it.
+            // The fetch time goes back to when the previous copy was downloaded.
+            let meta = CacheMeta {
+                fetched_at: CacheMeta::load(&previous).map(|meta| meta.fetched_at).unwrap_or_default(),
+                ..Default::default()
+            };
+            meta.save(path)?;
+
+            Ok(data)
+        }
+    }
+}
+
+impl CacheMeta {
+    /// Reads the sidecar of `path`, falling back to the file's modification time for caches
+    /// written before metadata was recorded
+    pub fn load(path: &str) -> Option<Self> {
+        if let Ok(data) = fs::read_to_string(meta_path(path)) {
+            if let Ok(meta) = serde_json::from_str(&data) {
+                return Some(meta);
+            }
+        }
+
+        let modified = fs::metadata(path).ok()?.modified().ok()?;
+        let fetched_at = modified.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+
+        Some(CacheMeta { fetched_at, ..Default::default() })
+    }
+
+    fn save(&self, path: &str) -> Result<(), Error> {
+        fs::write(meta_path(path), serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Drops the validators of `path`, so the next download is unconditional. Needed once the
+    /// local copy can't be read, otherwise the server keeps answering 304 for the broken file.
+    pub fn forget(path: &str) {
+        if fs::remove_file(meta_path(path)).is_ok() {
+            println!("Discarded cache validators of unreadable {}", path);
+        }
+    }
+
+    /// Marks `path` as fetched just now, keeping its validators
+    pub fn touch(path: &str) -> Result<(), Error> {
+        let meta = CacheMeta {
+            fetched_at: now(),
+            ..CacheMeta::load(path).unwrap_or_default()
+        };
+        meta.save(path)
+    }
+}
+
+impl Fetched {
+    /// Stores the validators of this download as the metadata of `path`
+    pub fn record(&self, path: &str) -> Result<(), Error> {
+        let meta = CacheMeta {
+            fetched_at: now(),
+            etag: self.etag.clone(),
+            last_modified: self.last_modified.clone(),
+        };
+        meta.save(path)
+    }
+}
+
+impl Fetched {
+    /// Validates a downloaded JSON body and atomically moves it into `path`.
+    /// The body must parse and hold at least `min_entries`, otherwise the cache is left untouched.
+    pub fn commit<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &[u8],
+        min_entries: usize,
+        count: impl Fn(&T) -> usize,
+    ) -> Result<T, Error> {
+        let data: T = serde_json::from_slice(body)
+            .map_err(|e| format!("Downloaded {} is not valid JSON: {}", path, e))?;
+
+        let entries = count(&data);
+        if entries < min_entries {
+            return Err(format!("Downloaded {} has only {} entries, expected at least {}", path, entries, min_entries).into());
+        }
+
+        let temp = format!("{}.tmp", path);
+        fs::write(&temp, body)?;
+
+        // Keep the current copy as `.prev` without moving it away, then replace it in one rename
+        // so `path` is never missing
+        if fs::metadata(path).is_ok() {
+            let previous = previous_path(path);
+            let _ = fs::remove_file(&previous);
+            if fs::hard_link(path, &previous).is_err() {
+                fs::copy(path, &previous)?;
+            }
+            // Remembers when the previous copy was fetched, in case it is rolled back to
+            let _ = fs::copy(meta_path(path), meta_path(&previous));
+        }
+        fs::rename(&temp, path)?;
+
+        self.record(path)?;
+        Ok(data)
+    }
+}
+
+fn header(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    response.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Downloads `url` unless the copy cached at `path` is still current. Returns `None` when the
+/// server answered 304 Not Modified, in which case only the fetch time of `path` is updated.
+pub async fn conditional_get(url: &str, path: &str) -> Result<Option<Fetched>, Error> {
+    let mut request = reqwest::Client::new().get(url);
+
+    // Validators are only worth sending if there is a local copy to fall back on
+    if fs::metadata(path).is_ok() {
+        if let Some(meta) = CacheMeta::load(path) {
+            if let Some(etag) = &meta.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &meta.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+    }
+
+    let response = request.send().await?;
+
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        println!("{} not modified upstream", path);
+        CacheMeta::touch(path)?;
+        return Ok(None);
+    }
+
+    let response = response.error_for_status()?;
+    let etag = header(&response, reqwest::header::ETAG);
+    let last_modified = header(&response, reqwest::header::LAST_MODIFIED);
+
+    Ok(Some(Fetched {
+        body: response.bytes().await?.into(),
+        etag,
+        last_modified,
+    }))
+}
+
+pub fn fetched_at(path: &str) -> Option<i64> {
+    CacheMeta::load(path).map(|meta| meta.fetched_at)
+}
+
+/// Whether the cached `path` was fetched within `max_age`
+pub fn is_fresh(path: &str, max_age: Duration) -> bool {
+    match fetched_at(path) {
+        Some(fetched_at) => now() - fetched_at <= max_age.as_secs() as i64,
+        None => false,
+    }
+}
+
+/// Short relative age such as `5m ago` or `3d ago`
+pub fn format_age(fetched_at: Option<i64>) -> String {
+    let Some(fetched_at) = fetched_at else {
+        return "unknown".to_string();
+    };
+
+    let seconds = (now() - fetched_at).max(0);
+
+    match seconds {
+        s if s < 60 => "just now".to_string(),
+        s if s < 60 * 60 => format!("{}m ago", s / 60),
+        s if s < 60 * 60 * 24 => format!("{}h ago", s / (60 * 60)),
+        s => format!("{}d ago", s / (60 * 60 * 24)),
+    }
+}
+
+/// Loads a cached dataset, downloading it again when it is missing or older than `max_age`.
+/// A stale copy is still used if the download fails.
+pub async fn load_or_refresh<T>(
+    path: &str,
+    config: &Config,
+    load: impl Future<Output = Result<T, Error>>,
+    refresh: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error> {
+    let max_age = config.max_data_age();
+
+    if config.offline {
+        return match load.await {
+            Ok(data) => {
+                if !is_fresh(path, max_age) {
+                    println!("Offline, using stale local {}", path);
+                } else {
+                    println!("Loaded local {}", path);
+                }
+                Ok(data)
+            },
+            Err(e) => {
+                println!("Offline and could not load {}", path);
+                Err(e)
+            }
+        };
+    }
+
+    // A fresh copy is all that's needed, anything older is only parsed if the download fails
+    if is_fresh(path, max_age) {
+        match load.await {
+            Ok(data) => {
+                println!("Loaded local {}", path);
+                return Ok(data);
+            },
+            Err(e) => {
+                println!("Could not load {}: {}", path, e);
+                CacheMeta::forget(path);
+                return match refresh.await {
+                    Ok(data) => {
+                        println!("Wrote new {}", path);
+                        Ok(data)
+                    },
+                    Err(e) => {
+                        println!("Failed to fetch {}", path);
+                        Err(e)
+                    }
+                };
+            }
+        }
+    }
+
+    println!("Local {} is missing or older than {}h, refreshing", path, max_age.as_secs() / (60 * 60));
+    match refresh.await {
+        Ok(data) => {
+            println!("Refreshed {}", path);
+            Ok(data)
+        },
+        Err(e) => match load.await {
+            Ok(stale) => {
+                eprintln!("Failed to refresh {}, using stale copy: {}", path, e);
+                Ok(stale)
+            },
+            Err(_) => {
+                println!("Failed to fetch {}", path);
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("feather-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn corrupt_file_rolls_back_and_forgets_validators() {
+        let dir = scratch("rollback");
+        let path = dir.join("prices.json").to_string_lossy().into_owned();
+
+        fs::write(&path, b"{ broken").unwrap();
+        fs::write(previous_path(&path), b"[1, 2, 3]").unwrap();
+        CacheMeta { fetched_at: 200, etag: Some("\"broken\"".to_string()), last_modified: None }.save(&path).unwrap();
+        CacheMeta { fetched_at: 100, ..Default::default() }.save(&previous_path(&path)).unwrap();
+
+        let data: Vec<u32> = read_json(&path).unwrap();
+        assert_eq!(data, vec![1, 2, 3]);
+        assert_eq!(fs::read(&path).unwrap(), b"[1, 2, 3]");
+
+        let meta = CacheMeta::load(&path).unwrap();
+        assert_eq!(meta.etag, None);
+        assert_eq!(meta.last_modified, None);
+        assert_eq!(meta.fetched_at, 100);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_file_without_previous_fails() {
+        let dir = scratch("no-previous");
+        let path = dir.join("all.json").to_string_lossy().into_owned();
+
+        fs::write(&path, b"{ broken").unwrap();
+        assert!(read_json::<Vec<u32>>(&path).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,136 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::priced_items::Quote;
+use crate::sources::SteamWindows;
+
+/// Number of agreeing markets at which the source count stops adding confidence
+const FULL_SOURCE_COUNT: f32 = 6.0;
+
+/// Scores below this are reported as low confidence
+pub const LOW_CONFIDENCE: f32 = 0.4;
+const HIGH_CONFIDENCE: f32 = 0.7;
+
+/// How far a suggested price can be trusted
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct Confidence {
+    /// Between 0 and 1
+    pub score: f32,
+    /// `(max - min) / min` across the aggregated quotes
+    pub spread: Option<f32>,
+}
+
+impl Confidence {
+    /// `dropped` are the outliers rejected before aggregation, markets that disagreed with the rest
+    pub fn compute(quotes: &[Quote], dropped: &[Quote], steam: Option<&SteamWindows>) -> Self {
+        if quotes.is_empty() {
+            return Confidence::default();
+        }
+
+        let source_factor = (quotes.len() as f32 / FULL_SOURCE_COUNT).min(1.0);
+
+        let min = quotes.iter().map(|quote| quote.price).min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        let max = quotes.iter().map(|quote| quote.price).max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+        // A single quote has no spread to speak of, so it earns no agreement credit either
+        let spread = match (min, max) {
+            (Some(min), Some(max)) if quotes.len() > 1 => Some(((max - min) / min) as f32),
+            _ => None,
+        };
+        // The spread only covers the markets that were kept, so it only vouches for their share
+        let agreement = quotes.len() as f32 / (quotes.len() + dropped.len()) as f32;
+        let spread_factor = spread.map(|spread| agreement / (1.0 + 2.0 * spread)).unwrap_or(0.0);
+
+        // The most recent Steam window with sales says how liquid the item is
+        let steam_factor = match steam {
+            Some(windows) if windows.last_24h.is_some_and(|p| p > 0.0) => 1.0,
+            Some(windows) if windows.last_7d.is_some_and(|p| p > 0.0) => 0.75,
+            Some(windows) if windows.last_30d.is_some_and(|p| p > 0.0) => 0.5,
+            Some(windows) if windows.last_90d.is_some_and(|p| p > 0.0) => 0.25,
+            _ => 0.0,
+        };
+
+        Confidence {
+            score: 0.4 * source_factor + 0.35 * spread_factor + 0.25 * steam_factor,
+            spread,
+        }
+    }
+
+    pub fn is_low(&self) -> bool {
+        self.score < LOW_CONFIDENCE
+    }
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let badge = if self.score >= HIGH_CONFIDENCE {
+            ":green_circle: High"
+        } else if self.is_low() {
+            ":red_circle: Low"
+        } else {
+            ":yellow_circle: Medium"
+        };
+
+        write!(f, "{} ({:.0}%)", badge, self.score * 100.0)?;
+
+        if let Some(spread) = self.spread {
+            write!(f, " · {:.0}% spread", spread * 100.0)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quotes(prices: &[f64]) -> Vec<Quote> {
+        prices.iter().enumerate().map(|(id, &price)| Quote::new(price, 1.0, id as u8)).collect()
+    }
+
+    #[test]
+    fn single_source_has_no_spread() {
+        let confidence = Confidence::compute(&quotes(&[10.0]), &[], None);
+
+        assert_eq!(confidence.spread, None);
+        assert!((confidence.score - 0.4 / FULL_SOURCE_COUNT).abs() < 1e-6);
+        assert!(confidence.is_low());
+    }
+
+    #[test]
+    fn tight_spread_beats_wide_spread() {
+        let tight = Confidence::compute(&quotes(&[10.0, 10.2, 10.5, 10.1]), &[], None);
+        let wide = Confidence::compute(&quotes(&[10.0, 14.0, 20.0, 12.0]), &[], None);
+
+        assert!((tight.spread.unwrap() - 0.05).abs() < 1e-6);
+        assert!((wide.spread.unwrap() - 1.0).abs() < 1e-6);
+        assert!(tight.score > wide.score);
+    }
+
+    #[test]
+    fn dropped_outliers_lower_the_score() {
+        let kept = quotes(&[10.0, 10.2, 10.5, 10.1]);
+        let agreeing = Confidence::compute(&kept, &[], None);
+        let disputed = Confidence::compute(&kept, &quotes(&[50.0, 1.0]), None);
+
+        assert_eq!(agreeing.spread, disputed.spread);
+        assert!(disputed.score < agreeing.score);
+    }
+
+    #[test]
+    fn recent_steam_sales_add_confidence() {
+        let kept = quotes(&[10.0, 10.2]);
+        let recent = SteamWindows { last_24h: Some(10.0), ..Default::default() };
+        let old = SteamWindows { last_90d: Some(10.0), ..Default::default() };
+
+        let with_recent = Confidence::compute(&kept, &[], Some(&recent)).score;
+        let with_old = Confidence::compute(&kept, &[], Some(&old)).score;
+        let without = Confidence::compute(&kept, &[], None).score;
+
+        assert!((with_recent - without - 0.25).abs() < 1e-6);
+        assert!(with_recent > with_old && with_old > without);
+    }
+}
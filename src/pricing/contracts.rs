@@ -0,0 +1,347 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::items::{variant_name, WEARS};
+use crate::priced_items::PricedItems;
+use crate::store::ItemStore;
+use crate::strategy::Strategy;
+
+/// Number of skins a contract takes
+pub const INPUTS: usize = 10;
+
+/// Covert skins can't be traded up into weapon skins
+const MAX_INPUT_GRADE: u8 = 4;
+
+/// A weapon skin that can go into or come out of a contract, without exterior or StatTrak
+#[derive(Debug, Clone)]
+pub struct Skin {
+    pub name: String,
+    pub grade: u8,
+    pub min_float: f64,
+    pub max_float: f64,
+    pub collections: Vec<String>,
+}
+
+/// Skins grouped by collection and grade, built once per item table
+pub struct TradeupCatalog {
+    skins: HashMap<String, Skin>,
+    by_collection: HashMap<(String, u8), Vec<String>>,
+}
+
+/// One skin a contract can produce
+#[derive(Debug, Clone)]
+pub struct TradeupOutcome {
+    /// Market hash name at the estimated exterior
+    pub name: String,
+    pub chance: f64,
+    /// Possible output floats given the input exteriors
+    pub float_range: (f64, f64),
+    pub price: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Tradeup {
+    /// Market hash names of the inputs
+    pub inputs: Vec<String>,
+    pub cost: f64,
+    /// Over the priced outcomes
+    pub expected: f64,
+    /// Most likely first
+    pub outcomes: Vec<TradeupOutcome>,
+    pub stattrak: bool,
+}
+
+impl Tradeup {
+    pub fn roi(&self) -> f64 {
+        self.expected / self.cost - 1.0
+    }
+
+    pub fn profit_chance(&self) -> f64 {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.price.is_some_and(|price| price > self.cost))
+            .map(|outcome| outcome.chance)
+            .sum()
+    }
+}
+
+/// Splits `StatTrak™ AK-47 | Redline (Field-Tested)` into the base name, exterior and StatTrak flag
+fn parse_name(hash_name: &str) -> Option<(&str, &'static str, bool)> {
+    let (stattrak, rest) = match hash_name.strip_prefix("StatTrak™ ") {
+        Some(rest) => (true, rest),
+        None => (false, hash_name),
+    };
+
+    WEARS.iter().find_map(|&(wear, _, _)| {
+        let base = rest.strip_suffix(&format!(" ({})", wear))?;
+        Some((base, wear, stattrak))
+    })
+}
+
+fn wear_at(float: f64) -> &'static str {
+    WEARS
+        .iter()
+        .find(|&&(_, _, high)| float < high)
+        .map(|&(wear, _, _)| wear)
+        .unwrap_or("Battle-Scarred")
+}
+
+/// Float interval a skin can have at the given exterior
+fn wear_interval(skin: &Skin, wear: &str) -> Option<(f64, f64)> {
+    let &(_, low, high) = WEARS.iter().find(|(name, _, _)| *name == wear)?;
+    let (low, high) = (low.max(skin.min_float), high.min(skin.max_float));
+    (low < high).then_some((low, high))
+}
+
+fn price_of(name: &str, store: &ItemStore, strategy: Option<&Strategy>) -> Option<f64> {
+    store.item_data.get(name)?.suggested(strategy).filter(|&price| price > 0.0)
+}
+
+impl TradeupCatalog {
+    pub fn build(item_data: &PricedItems) -> Self {
+        let mut skins: HashMap<String, Skin> = HashMap::new();
+
+        for priced in item_data.values() {
+            let info = &priced.info;
+            if info.weapon.is_none() || info.stattrak || info.souvenir || info.phase.is_some() || info.collections.is_empty() {
+                continue;
+            }
+
+            let (Some(hash_name), Some(grade), Some(min_float), Some(max_float)) = (
+                info.market_hash_name.as_deref(),
+                info.rarity.as_ref().and_then(|rarity| rarity.grade()),
+                info.min_float,
+                info.max_float,
+            ) else {
+                continue;
+            };
+
+            let Some((base, _, false)) = parse_name(hash_name) else { continue };
+
+            skins.entry(base.to_string()).or_insert_with(|| Skin {
+                name: base.to_string(),
+                grade,
+                min_float,
+                max_float,
+                collections: info.collections.iter().map(|collection| collection.id.clone()).collect(),
+            });
+        }
+
+        let mut by_collection: HashMap<(String, u8), Vec<String>> = HashMap::new();
+        for skin in skins.values() {
+            for collection in &skin.collections {
+                by_collection.entry((collection.clone(), skin.grade)).or_default().push(skin.name.clone());
+            }
+        }
+
+        TradeupCatalog { skins, by_collection }
+    }
+
+    /// Skins of the next grade an input can turn into, per collection it belongs to
+    fn next_grade(&self, skin: &Skin) -> Vec<&Vec<String>> {
+        skin.collections
+            .iter()
+            .filter_map(|collection| self.by_collection.get(&(collection.clone(), skin.grade + 1)))
+            .collect()
+    }
+
+    /// Chance of each next grade skin. Every next grade skin of every input's collections goes into
+    /// one pool, once per input, and the outcome is drawn uniformly from that pool.
+    fn outcome_chances(&self, inputs: &[&Skin]) -> HashMap<&str, f64> {
+        let mut tickets: HashMap<&str, f64> = HashMap::new();
+        for skin in inputs {
+            for pool in self.next_grade(skin) {
+                for name in pool {
+                    *tickets.entry(name.as_str()).or_default() += 1.0;
+                }
+            }
+        }
+
+        let total: f64 = tickets.values().sum();
+        for chance in tickets.values_mut() {
+            *chance /= total;
+        }
+
+        tickets
+    }
+
+    /// Outcomes of a contract from the market hash names of its inputs
+    pub fn evaluate(&self, inputs: &[String], store: &ItemStore, strategy: Option<&Strategy>) -> Result<Tradeup, String> {
+        if inputs.len() != INPUTS {
+            return Err(format!("A trade-up takes exactly {} skins", INPUTS));
+        }
+
+        let mut parsed: Vec<(&Skin, (f64, f64))> = vec![];
+        let mut stattrak = None;
+        let mut cost = 0.0;
+
+        for input in inputs {
+            if input.starts_with("Souvenir ") {
+                return Err(format!("Souvenir skins can't be traded up: {}", input));
+            }
+
+            let (base, wear, input_stattrak) = parse_name(input)
+                .ok_or(format!("Not a weapon skin: {}", input))?;
+            let skin = self.skins.get(base)
+                .ok_or(format!("No collection found for {}", input))?;
+            let interval = wear_interval(skin, wear)
+                .ok_or(format!("{} doesn't come in {}", base, wear))?;
+
+            if *stattrak.get_or_insert(input_stattrak) != input_stattrak {
+                return Err("StatTrak™ and regular skins can't be mixed".to_string());
+            }
+            if skin.grade > MAX_INPUT_GRADE {
+                return Err(format!("{} is too rare to trade up", input));
+            }
+            if skin.grade != parsed.first().map(|(first, _)| first.grade).unwrap_or(skin.grade) {
+                return Err("All skins must be of the same grade".to_string());
+            }
+            if self.next_grade(skin).is_empty() {
+                return Err(format!("{} has no higher grade in its collection", input));
+            }
+
+            cost += price_of(input, store, strategy).ok_or(format!("No price for {}", input))?;
+            parsed.push((skin, interval));
+        }
+
+        let stattrak = stattrak.unwrap_or(false);
+
+        // Output float is out_min + average input float × (out_max - out_min)
+        let average_low = parsed.iter().map(|(_, (low, _))| low).sum::<f64>() / INPUTS as f64;
+        let average_high = parsed.iter().map(|(_, (_, high))| high).sum::<f64>() / INPUTS as f64;
+
+        let skins: Vec<&Skin> = parsed.iter().map(|(skin, _)| *skin).collect();
+        let mut outcomes: Vec<TradeupOutcome> = self.outcome_chances(&skins)
+            .into_iter()
+            .filter_map(|(name, chance)| {
+                let skin = self.skins.get(name)?;
+                let range = skin.max_float - skin.min_float;
+                let float_range = (skin.min_float + average_low * range, skin.min_float + average_high * range);
+
+                let estimated = wear_at((float_range.0 + float_range.1) / 2.0);
+                let hash_name = variant_name(&format!("{} ({})", name, estimated), if stattrak { "StatTrak™ " } else { "" });
+                let price = price_of(&hash_name, store, strategy);
+
+                Some(TradeupOutcome { name: hash_name, chance, float_range, price })
+            })
+            .collect();
+
+        outcomes.sort_by(|a, b| b.chance.partial_cmp(&a.chance).unwrap_or(Ordering::Equal).then_with(|| a.name.cmp(&b.name)));
+
+        let priced_chance: f64 = outcomes.iter().filter(|outcome| outcome.price.is_some()).map(|outcome| outcome.chance).sum();
+        let expected = if priced_chance > 0.0 {
+            outcomes.iter().filter_map(|outcome| outcome.price.map(|price| price * outcome.chance)).sum::<f64>() / priced_chance
+        } else {
+            0.0
+        };
+
+        Ok(Tradeup {
+            inputs: inputs.to_vec(),
+            cost,
+            expected,
+            outcomes,
+            stattrak,
+        })
+    }
+
+    /// Most profitable contracts of ten identical inputs priced between `min_price` and `max_price` each
+    pub fn search(&self, min_price: f64, max_price: f64, store: &ItemStore, strategy: Option<&Strategy>, limit: usize) -> Vec<Tradeup> {
+        let mut found: Vec<Tradeup> = vec![];
+
+        for skin in self.skins.values() {
+            if skin.grade > MAX_INPUT_GRADE || self.next_grade(skin).is_empty() {
+                continue;
+            }
+
+            for (wear, _, _) in WEARS {
+                for prefix in ["", "StatTrak™ "] {
+                    let input = variant_name(&format!("{} ({})", skin.name, wear), prefix);
+                    let Some(price) = price_of(&input, store, strategy) else { continue };
+                    if price < min_price || price > max_price {
+                        continue;
+                    }
+
+                    let inputs = vec![input; INPUTS];
+                    if let Ok(tradeup) = self.evaluate(&inputs, store, strategy) {
+                        // Contracts with unpriced outcomes would be ranked on partial data
+                        if tradeup.outcomes.iter().all(|outcome| outcome.price.is_some()) {
+                            found.push(tradeup);
+                        }
+                    }
+                }
+            }
+        }
+
+        found.sort_by(|a, b| b.roi().partial_cmp(&a.roi()).unwrap_or(Ordering::Equal));
+        found.truncate(limit);
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skin(name: &str, grade: u8, collection: &str) -> Skin {
+        Skin {
+            name: name.to_string(),
+            grade,
+            min_float: 0.0,
+            max_float: 1.0,
+            collections: vec![collection.to_string()],
+        }
+    }
+
+    fn catalog(skins: Vec<Skin>) -> TradeupCatalog {
+        let mut by_collection: HashMap<(String, u8), Vec<String>> = HashMap::new();
+        for skin in &skins {
+            for collection in &skin.collections {
+                by_collection.entry((collection.clone(), skin.grade)).or_default().push(skin.name.clone());
+            }
+        }
+
+        TradeupCatalog {
+            skins: skins.into_iter().map(|skin| (skin.name.clone(), skin)).collect(),
+            by_collection,
+        }
+    }
+
+    #[test]
+    fn mixed_collections_share_one_pool() {
+        let catalog = catalog(vec![
+            skin("Input A", 2, "a"),
+            skin("Output A", 3, "a"),
+            skin("Input B", 2, "b"),
+            skin("Output B1", 3, "b"),
+            skin("Output B2", 3, "b"),
+            skin("Output B3", 3, "b"),
+        ]);
+
+        let input_a = &catalog.skins["Input A"];
+        let input_b = &catalog.skins["Input B"];
+        let mut inputs = vec![input_a];
+        inputs.extend(std::iter::repeat_n(input_b, 9));
+
+        let chances = catalog.outcome_chances(&inputs);
+
+        assert!((chances["Output A"] - 1.0 / 28.0).abs() < 1e-12);
+        for name in ["Output B1", "Output B2", "Output B3"] {
+            assert!((chances[name] - 9.0 / 28.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn single_collection_is_uniform() {
+        let catalog = catalog(vec![
+            skin("Input", 1, "a"),
+            skin("Output 1", 2, "a"),
+            skin("Output 2", 2, "a"),
+        ]);
+
+        let input = &catalog.skins["Input"];
+        let chances = catalog.outcome_chances(&[input; INPUTS]);
+
+        assert!((chances["Output 1"] - 0.5).abs() < 1e-12);
+        assert!((chances["Output 2"] - 0.5).abs() < 1e-12);
+    }
+}
@@ -1,9 +1,10 @@
 use std::env;
-use std::fs;
 use std::collections::HashMap;
 use serde::Deserialize;
 
-use crate::Context;
+use crate::cache::{conditional_get, fetched_at, load_or_refresh, read_json};
+use crate::config::Config;
+use crate::Error;
 
 macro_rules! create_currency_formats {
     ($($code:expr => $format:expr),*) => {{
@@ -20,30 +21,65 @@ pub struct ExchangeRates {
     pub conversion_rates: HashMap<String, f64>,
 }
 
-pub async fn exchange(amount: f64, code: &str, ctx: &Context<'_>) -> String {
-    ctx.data().currency_formats.get(code).unwrap_or(&"${}".to_string()).replace("{}", 
-        &format!("{:.2}", amount * ctx.data().conversion_rates.get(code).unwrap_or(&1.0)))
+/// Exchange rates and display formats, loaded in the background at startup
+pub struct Currencies {
+    /// Supported currency codes, sorted for autocomplete
+    pub codes: Vec<String>,
+    pub formats: HashMap<String, String>,
+    pub rates: HashMap<String, f64>,
+    /// Unix seconds when the rates were downloaded
+    pub fetched_at: Option<i64>,
+    /// Stand-in used while the rates are unavailable
+    pub usd_only: bool,
 }
 
-const EXCHANGE_FILE: &str = "exchange.json";
+impl Currencies {
+    pub fn usd_only() -> Self {
+        Currencies {
+            codes: vec!["USD".to_string()],
+            formats: HashMap::from([("USD".to_string(), "${}".to_string())]),
+            rates: HashMap::from([("USD".to_string(), 1.0)]),
+            fetched_at: None,
+            usd_only: true,
+        }
+    }
+
+    /// The currency amounts are actually shown in for a user who picked `code`
+    pub fn shown_code<'a>(&self, code: &'a str) -> &'a str {
+        if self.usd_only {
+            "USD"
+        } else {
+            code
+        }
+    }
+
+    pub fn exchange(&self, amount: f64, code: &str) -> String {
+        self.formats.get(code).unwrap_or(&"${}".to_string()).replace("{}",
+            &format!("{:.2}", amount * self.rates.get(code).unwrap_or(&1.0)))
+    }
+}
+
+pub const EXCHANGE_FILE: &str = "exchange.json";
 const EXCHANGE_API: &str = "https://v6.exchangerate-api.com/v6/{}/latest/USD";
 
-async fn load_json() -> Result<ExchangeRates, Box<dyn std::error::Error>> {
-    let data = fs::read_to_string(EXCHANGE_FILE)?;
-    let rates: ExchangeRates = serde_json::from_str(&data)?;
-    Ok(rates)
+/// Sanity floor for a downloaded rate table, the real one covers over 150 currencies
+const MIN_RATES: usize = 100;
+
+async fn load_json(path: &str) -> Result<ExchangeRates, Error> {
+    read_json(path)
 }
 
-pub async fn refresh_json() -> Result<ExchangeRates, Box<dyn std::error::Error>> {
-    let response = reqwest::get(
-        EXCHANGE_API.replace("{}", &env::var("EXCHANGERATE_TOKEN").expect("Exchange rate token missing"))
-    ).await?.text().await?;
-    let rates: ExchangeRates = serde_json::from_str(&response)?;
-    fs::write(EXCHANGE_FILE, response)?;
-    Ok(rates)
+pub async fn refresh_json(path: &str) -> Result<ExchangeRates, Error> {
+    let token = env::var("EXCHANGERATE_TOKEN").map_err(|_| "Exchange rate token missing")?;
+    let url = EXCHANGE_API.replace("{}", &token);
+
+    match conditional_get(&url, path).await? {
+        Some(fetched) => fetched.commit(path, &fetched.body, MIN_RATES, |rates: &ExchangeRates| rates.conversion_rates.len()),
+        None => load_json(path).await,
+    }
 }
 
-pub async fn load_exchange_rates() -> Result<(ExchangeRates, HashMap<String, String>), Box<dyn std::error::Error>> {
+pub async fn load_exchange_rates(config: &Config) -> Result<Currencies, Error> {
     let currency_formats: HashMap<String, String> = create_currency_formats!(
         "USD" => "${}",
         "AED" => "{} د.إ",
@@ -209,25 +245,17 @@ pub async fn load_exchange_rates() -> Result<(ExchangeRates, HashMap<String, Str
         "ZWL" => "{} Z$"
     );
 
-    let rates: ExchangeRates = match load_json().await {
-        Ok(rates) => {
-            println!("Loaded local {}", EXCHANGE_FILE);
-            rates
-        },
-        Err(_) => {
-            println!("Could not find {}", EXCHANGE_FILE);
-            match refresh_json().await {
-                Ok(rates) => {
-                    println!("Wrote new {}", EXCHANGE_FILE);
-                    rates
-                },
-                Err(e) => {
-                    println!("Failed to fetch exchange rates");
-                    return Err(e);
-                }
-            }
-        }
-    };
+    let path = config.data_path(EXCHANGE_FILE);
+    let rates = load_or_refresh(&path, config, load_json(&path), refresh_json(&path)).await?;
+
+    let mut codes: Vec<String> = currency_formats.keys().cloned().collect();
+    codes.sort();
 
-    Ok((rates, currency_formats))
+    Ok(Currencies {
+        codes,
+        formats: currency_formats,
+        rates: rates.conversion_rates,
+        fetched_at: fetched_at(&path),
+        usd_only: false,
+    })
 }
\ No newline at end of file
@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::config::Config;
+use crate::items::Item;
+use crate::Error;
+
+/// Hand-maintained icon to phase overrides, for icons the catalog gets wrong or lacks
+pub const LOCAL_DOPPLER: &str = "doppler.json";
+
+/// Key of a phased item in the item table, e.g. `★ Karambit | Doppler (Factory New) Ruby`
+pub fn phased_name(hash_name: &str, phase: &str) -> String {
+    format!("{} {}", hash_name, phase)
+}
+
+/// The Steam economy image id inside a CDN URL, which is what inventories report as `icon_url`
+fn icon_id(image: &str) -> Option<&str> {
+    let (_, rest) = image.split_once("/economy/image/")?;
+    rest.split('/').next().filter(|id| !id.is_empty())
+}
+
+/// Maps inventory icons to phases, from the catalog's phase images plus `doppler.json` overrides
+pub async fn load_doppler(item_info: &HashMap<String, Item>, config: &Config) -> Result<HashMap<String, String>, Error> {
+    let mut dopplers: HashMap<String, String> = item_info
+        .values()
+        .filter_map(|item| {
+            let phase = item.phase.as_ref()?;
+            let icon = icon_id(item.image.as_ref()?)?;
+            Some((icon.to_string(), phase.clone()))
+        })
+        .collect();
+
+    let derived = dopplers.len();
+
+    match fs::read_to_string(config.data_path(LOCAL_DOPPLER)) {
+        Ok(data) => {
+            let overrides: HashMap<String, String> = serde_json::from_str(&data)?;
+            dopplers.extend(overrides);
+        },
+        Err(e) if derived == 0 => return Err(e.into()),
+        Err(_) => println!("Could not find {}, using catalog phases only", LOCAL_DOPPLER),
+    }
+
+    println!("Loaded {} doppler icons ({} from catalog)", dopplers.len(), derived);
+
+    Ok(dopplers)
+}
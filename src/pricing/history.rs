@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use crate::cache::now;
+use crate::database::models::PricePoint;
+use crate::database::DatabaseManager;
+use crate::store::ItemStore;
+use crate::Error;
+
+const SECONDS_PER_DAY: i64 = 60 * 60 * 24;
+
+pub fn today() -> i64 {
+    now() / SECONDS_PER_DAY
+}
+
+/// Stores today's prices of every item, unless today already has a complete snapshot.
+/// A day cut short (e.g. by a restart) is completed, items already stored are skipped as duplicates.
+pub async fn record_snapshot(store: &ItemStore, db: &Arc<Mutex<DatabaseManager>>) -> Result<(), Error> {
+    let day = today();
+
+    let points: Vec<PricePoint> = store.item_data
+        .iter()
+        .filter(|(_, priced)| priced.feather.is_some())
+        .map(|(name, priced)| PricePoint {
+            name: name.to_string(),
+            day,
+            feather: priced.feather,
+            steam: priced.steam(),
+            skinport: priced.skinport(),
+            buff: priced.buff(),
+        })
+        .collect();
+
+    if points.is_empty() {
+        return Ok(());
+    }
+
+    // Thousands of inserts take a while, commands keep the database meanwhile
+    let history = db.lock().await.price_history();
+    if history.count_snapshot(day).await? >= points.len() as u64 {
+        return Ok(());
+    }
+
+    let count = points.len();
+    history.insert_snapshot(points).await?;
+    println!("Recorded price history for {} items", count);
+
+    Ok(())
+}
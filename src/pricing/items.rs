@@ -1,58 +1,197 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs;
 
-#[derive(Deserialize, Clone, Debug)]
+use crate::cache::{blocking, conditional_get, load_or_refresh, read_json, CacheMeta};
+use crate::config::Config;
+use crate::lenient::{settle, valid_count};
+use crate::Error;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Rarity {
+    pub id: Option<String>,
+    pub name: Option<String>,
     pub color: Option<String>
 }
 
-#[derive(Deserialize, Clone, Debug)]
+/// An `{ id, name }` pair such as a weapon, category or finish
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Named {
+    pub id: Option<String>,
+    pub name: Option<String>,
+}
+
+/// A collection or crate an item belongs to
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Reference {
+    pub id: String,
+    pub name: String,
+    pub image: Option<String>,
+}
+
+/// One possible drop of a container, named without wear or StatTrak
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Drop {
+    pub id: Option<String>,
+    pub name: String,
+    pub rarity: Option<Rarity>,
+    pub paint_index: Option<String>,
+    pub phase: Option<String>,
+    pub image: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Item {
-    // pub id: String,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub description: Option<String>,
     pub market_hash_name: Option<String>,
     pub image: Option<String>,
     pub rarity: Option<Rarity>,
 
-    pub phase: Option<String>
+    pub phase: Option<String>,
+
+    pub weapon: Option<Named>,
+    pub category: Option<Named>,
+    /// Finish of a skin, e.g. `Case Hardened`
+    pub pattern: Option<Named>,
+    pub wear: Option<Named>,
+    pub min_float: Option<f64>,
+    pub max_float: Option<f64>,
+    pub paint_index: Option<String>,
+    #[serde(default)]
+    pub stattrak: bool,
+    #[serde(default)]
+    pub souvenir: bool,
+    #[serde(default)]
+    pub collections: Vec<Reference>,
+    /// Containers that can drop this item
+    #[serde(default)]
+    pub crates: Vec<Reference>,
+
+    /// Container type such as `Case` or `Sticker Capsule`, also set on stickers and other non-skins
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    /// Regular drops of a container
+    #[serde(default)]
+    pub contains: Vec<Drop>,
+    /// Rare special items of a container, such as knives and gloves
+    #[serde(default)]
+    pub contains_rare: Vec<Drop>,
 }
 
-const LOCAL_FILE: &str = "all.json";
-const API_URL: &str = "https://bymykel.github.io/CSGO-API/api/en/all.json";
+/// Exteriors with the float range each covers
+pub const WEARS: [(&str, f64, f64); 5] = [
+    ("Factory New", 0.0, 0.07),
+    ("Minimal Wear", 0.07, 0.15),
+    ("Field-Tested", 0.15, 0.38),
+    ("Well-Worn", 0.38, 0.45),
+    ("Battle-Scarred", 0.45, 1.0),
+];
 
-async fn load_json() -> Result<HashMap<String, Item>, Box<dyn std::error::Error>> {
-    let data = fs::read_to_string(LOCAL_FILE)?;
-    let items: HashMap<String, Item> = serde_json::from_str(&data)?;
-    Ok(items)
+/// `name` as its StatTrak or Souvenir copy (or plain with an empty `prefix`), keeping the knife and glove star in front
+pub fn variant_name(name: &str, prefix: &str) -> String {
+    let (star, rest) = match name.strip_prefix("★ ") {
+        Some(rest) => ("★ ", rest),
+        None => ("", name),
+    };
+    let rest = rest
+        .strip_prefix("StatTrak™ ")
+        .or_else(|| rest.strip_prefix("Souvenir "))
+        .unwrap_or(rest);
+
+    format!("{}{}{}", star, prefix, rest)
 }
 
-pub async fn refresh_json() -> Result<HashMap<String, Item>, Box<dyn std::error::Error>> {
-    let response = reqwest::get(API_URL).await?.text().await?;
-    let items: HashMap<String, Item> = serde_json::from_str(&response)?;
-    fs::write(LOCAL_FILE, response)?;
-    Ok(items)
+impl Rarity {
+    /// Position of the grade from Consumer (0) up to Contraband, stickers and agents share the weapon scale
+    pub fn grade(&self) -> Option<u8> {
+        let id = self.id.as_deref()?.strip_prefix("rarity_")?;
+        let id = id
+            .strip_suffix("_weapon")
+            .or_else(|| id.strip_suffix("_character"))
+            .unwrap_or(id);
+
+        match id {
+            "common" => Some(0),
+            "uncommon" => Some(1),
+            "rare" => Some(2),
+            "mythical" => Some(3),
+            "legendary" => Some(4),
+            "ancient" => Some(5),
+            "contraband" => Some(6),
+            _ => None,
+        }
+    }
+
+    /// Parses the `#rrggbb` color
+    pub fn rgb(&self) -> Option<(u8, u8, u8)> {
+        let color = self.color.as_deref()?.strip_prefix('#')?;
+        if color.len() != 6 {
+            return None;
+        }
+
+        Some((
+            u8::from_str_radix(color.get(0..2)?, 16).ok()?,
+            u8::from_str_radix(color.get(2..4)?, 16).ok()?,
+            u8::from_str_radix(color.get(4..6)?, 16).ok()?,
+        ))
+    }
+}
+
+impl Item {
+    pub fn rarity_rgb(&self) -> Option<(u8, u8, u8)> {
+        self.rarity.as_ref().and_then(Rarity::rgb)
+    }
+
+    /// Whether this can be opened for one of the `contains` drops
+    pub fn is_container(&self) -> bool {
+        !self.contains.is_empty()
+    }
 }
 
-pub async fn scrape_items() -> Result<HashMap<String, Item>, Box<dyn std::error::Error>> {
-    let items: HashMap<String, Item> = match load_json().await {
-        Ok(items) => {
-            println!("Loaded local {}", LOCAL_FILE);
-            items
+pub const LOCAL_FILE: &str = "all.json";
+const API_URL: &str = "https://bymykel.github.io/CSGO-API/api/en/all.json";
+
+/// Sanity floor for a downloaded catalog, the real one has tens of thousands of entries
+const MIN_ITEMS: usize = 1000;
+
+async fn load_json(path: &str) -> Result<HashMap<String, Item>, Error> {
+    let path = path.to_string();
+    blocking(move || Ok(settle(read_json(&path)?, &path))).await
+}
+
+pub async fn refresh_json(path: &str) -> Result<HashMap<String, Item>, Error> {
+    let fetched = match conditional_get(API_URL, path).await? {
+        Some(fetched) => fetched,
+        None => match load_json(path).await {
+            Ok(items) => return Ok(items),
+            Err(e) => {
+                // The copy the validators vouch for is gone or broken, only a full download helps
+                eprintln!("{} was not modified upstream but could not be loaded, downloading it again: {}", path, e);
+                CacheMeta::forget(path);
+                conditional_get(API_URL, path).await?.ok_or("Item catalog answered 304 without validators")?
+            },
         },
-        Err(_) => {
-            println!("Could not find {}", LOCAL_FILE);
-            match refresh_json().await {
-                Ok(items) => {
-                    println!("Wrote new {}", LOCAL_FILE);
-                    items
-                },
-                Err(e) => {
-                    println!("Failed to fetch items");
-                    return Err(e);
-                }
-            }
-        }
     };
 
-    return Ok(items);
-}
\ No newline at end of file
+    let path = path.to_string();
+    blocking(move || Ok(settle(fetched.commit(&path, &fetched.body, MIN_ITEMS, valid_count)?, &path))).await
+}
+
+pub async fn scrape_items(config: &Config) -> Result<HashMap<String, Item>, Error> {
+    let path = config.data_path(LOCAL_FILE);
+    load_or_refresh(&path, config, load_json(&path), refresh_json(&path)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variant_name_keeps_the_star_in_front() {
+        assert_eq!(variant_name("★ Karambit | Fade (Factory New)", "StatTrak™ "), "★ StatTrak™ Karambit | Fade (Factory New)");
+        assert_eq!(variant_name("StatTrak™ AK-47 | Redline (Field-Tested)", ""), "AK-47 | Redline (Field-Tested)");
+        assert_eq!(variant_name("StatTrak™ AK-47 | Redline (Field-Tested)", "Souvenir "), "Souvenir AK-47 | Redline (Field-Tested)");
+        assert_eq!(variant_name("★ StatTrak™ Karambit | Fade (Factory New)", ""), "★ Karambit | Fade (Factory New)");
+    }
+}
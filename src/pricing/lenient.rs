@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+/// Deserializes to `None` instead of failing, so one malformed entry doesn't sink a whole file
+#[derive(Debug)]
+pub struct Lenient<T>(pub Option<T>);
+
+impl<'de, T: for<'a> Deserialize<'a>> Deserialize<'de> for Lenient<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        Ok(Lenient(T::deserialize(value).ok()))
+    }
+}
+
+/// Number of entries that parsed
+pub fn valid_count<T>(entries: &HashMap<String, Lenient<T>>) -> usize {
+    entries.values().filter(|Lenient(entry)| entry.is_some()).count()
+}
+
+/// Drops the entries that failed to parse, reporting how many there were
+pub fn settle<T>(entries: HashMap<String, Lenient<T>>, what: &str) -> HashMap<String, T> {
+    let total = entries.len();
+
+    let parsed: HashMap<String, T> = entries
+        .into_iter()
+        .filter_map(|(key, Lenient(entry))| entry.map(|entry| (key, entry)))
+        .collect();
+
+    if parsed.len() < total {
+        eprintln!("Skipped {} malformed entries in {}", total - parsed.len(), what);
+    }
+
+    parsed
+}
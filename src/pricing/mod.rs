@@ -1,3 +1,19 @@
 pub mod items;
+pub mod lenient;
+pub mod outliers;
+pub mod patterns;
 pub mod priced_items;
-pub mod currency;
\ No newline at end of file
+pub mod search;
+pub mod applied;
+pub mod cache;
+pub mod confidence;
+pub mod currency;
+pub mod doppler;
+pub mod history;
+pub mod snapshot;
+pub mod sources;
+pub mod store;
+pub mod strategy;
+pub mod trend;
+pub mod unboxing;
+pub mod contracts;
\ No newline at end of file
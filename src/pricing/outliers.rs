@@ -0,0 +1,159 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use crate::priced_items::Quote;
+
+/// Below this many quotes there is no majority to judge an outlier against
+const MIN_QUOTES: usize = 3;
+
+/// How anomalous market quotes are detected before aggregation
+#[derive(Clone, Debug, PartialEq)]
+pub enum OutlierFilter {
+    Disabled,
+    /// Modified z-score cutoff over log prices, using the median absolute deviation
+    Mad(f64),
+    /// Maximum ratio between a quote and the median, in either direction
+    Ratio(f64),
+}
+
+impl Default for OutlierFilter {
+    fn default() -> Self {
+        OutlierFilter::Ratio(3.0)
+    }
+}
+
+impl FromStr for OutlierFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, arg) = match s.trim().split_once(':') {
+            Some((name, arg)) => (name, Some(arg.trim())),
+            None => (s.trim(), None),
+        };
+
+        let parse_arg = |default: f64| match arg {
+            Some(arg) => arg.parse::<f64>()
+                .ok()
+                .filter(|&cutoff| cutoff > 0.0 && cutoff.is_finite())
+                .ok_or(format!("Invalid outlier cutoff `{}`", arg)),
+            None => Ok(default),
+        };
+
+        match name.to_lowercase().as_str() {
+            "none" => Ok(OutlierFilter::Disabled),
+            "mad" => Ok(OutlierFilter::Mad(parse_arg(3.5)?)),
+            "ratio" => {
+                // A ratio of 1 or less calls every quote an outlier, which the majority rule then ignores
+                let ratio = parse_arg(3.0)?;
+                if ratio <= 1.0 {
+                    return Err(format!("Outlier ratio must be above 1, got `{}`", ratio));
+                }
+                Ok(OutlierFilter::Ratio(ratio))
+            },
+            _ => Err(format!("Unknown outlier filter `{}`", name)),
+        }
+    }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+    let n = values.len();
+    if n % 2 == 1 {
+        values[n / 2]
+    } else {
+        (values[n / 2 - 1] + values[n / 2]) / 2.0
+    }
+}
+
+impl OutlierFilter {
+    /// Splits quotes into `(kept, dropped)`. Only a minority is ever dropped, when the quotes
+    /// split into camps of similar size there is no telling which one is wrong and all are kept.
+    pub fn apply(&self, quotes: Vec<Quote>) -> (Vec<Quote>, Vec<Quote>) {
+        if quotes.len() < MIN_QUOTES {
+            return (quotes, vec![]);
+        }
+
+        // Prices are compared on a log scale so that 2x too high and 2x too low count the same
+        let mut logs: Vec<f64> = quotes.iter().map(|quote| quote.price.ln()).collect();
+        let center = median(&mut logs);
+
+        let is_outlier: Box<dyn Fn(&Quote) -> bool> = match self {
+            OutlierFilter::Disabled => return (quotes, vec![]),
+            OutlierFilter::Mad(cutoff) => {
+                let mut deviations: Vec<f64> = quotes.iter().map(|quote| (quote.price.ln() - center).abs()).collect();
+                let mad = median(&mut deviations);
+
+                // Most quotes agree exactly, nothing meaningful to scale by
+                if mad == 0.0 {
+                    return (quotes, vec![]);
+                }
+
+                let cutoff = *cutoff;
+                Box::new(move |quote| 0.6745 * (quote.price.ln() - center).abs() / mad > cutoff)
+            },
+            OutlierFilter::Ratio(ratio) => {
+                let max_distance = ratio.ln();
+                Box::new(move |quote| (quote.price.ln() - center).abs() > max_distance)
+            },
+        };
+
+        let (kept, dropped): (Vec<Quote>, Vec<Quote>) = quotes.into_iter().partition(|quote| !is_outlier(quote));
+
+        if dropped.len() * 2 >= kept.len() + dropped.len() {
+            let mut all = kept;
+            all.extend(dropped);
+            return (all, vec![]);
+        }
+
+        (kept, dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quotes(prices: &[f64]) -> Vec<Quote> {
+        prices.iter().map(|&price| Quote::new(price, 1.0, 0)).collect()
+    }
+
+    fn prices(quotes: &[Quote]) -> Vec<f64> {
+        quotes.iter().map(|quote| quote.price).collect()
+    }
+
+    #[test]
+    fn drops_a_lone_outlier() {
+        let (kept, dropped) = OutlierFilter::default().apply(quotes(&[10.0, 11.0, 9.5, 100.0]));
+
+        assert_eq!(prices(&kept), vec![10.0, 11.0, 9.5]);
+        assert_eq!(prices(&dropped), vec![100.0]);
+    }
+
+    #[test]
+    fn keeps_everything_when_quotes_split_evenly() {
+        let (kept, dropped) = OutlierFilter::default().apply(quotes(&[1.0, 1.0, 10.0, 10.0]));
+
+        assert_eq!(kept.len(), 4);
+        assert!(dropped.is_empty());
+    }
+
+    #[test]
+    fn parses_filters() {
+        assert_eq!("ratio:2".parse(), Ok(OutlierFilter::Ratio(2.0)));
+        assert_eq!("mad".parse(), Ok(OutlierFilter::Mad(3.5)));
+        assert_eq!("none".parse(), Ok(OutlierFilter::Disabled));
+        assert!("ratio:1".parse::<OutlierFilter>().is_err());
+        assert!("ratio:0.5".parse::<OutlierFilter>().is_err());
+        assert!("mad:inf".parse::<OutlierFilter>().is_err());
+        assert!("zscore".parse::<OutlierFilter>().is_err());
+    }
+
+    #[test]
+    fn keeps_few_quotes() {
+        let (kept, dropped) = OutlierFilter::default().apply(quotes(&[1.0, 100.0]));
+
+        assert_eq!(kept.len(), 2);
+        assert!(dropped.is_empty());
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::items::{variant_name, WEARS};
+
+/// Hand-maintained premiums for paint seeds that sell above the market price,
+/// such as Case Hardened blue gems, Fade percentages or Marble Fade fire and ice
+pub const LOCAL_PATTERNS: &str = "patterns.json";
+
+/// Paint seeds run from 0 to 1000
+const SEEDS: usize = 1001;
+
+/// Fade percentages are quoted on a scale from the least faded seed at 80% to the most at 100%
+const FADE_FLOOR: f64 = 80.0;
+
+/// A group of paint seeds worth `multiplier` times the market price of the skin
+#[derive(Deserialize, Debug, Clone)]
+pub struct PatternTier {
+    pub name: String,
+    pub multiplier: f64,
+    pub seeds: HashSet<u32>,
+}
+
+/// A premium reached by a fade percentage of at least `min_percentage`
+#[derive(Deserialize, Debug, Clone)]
+pub struct FadeTier {
+    pub name: String,
+    pub min_percentage: f64,
+    pub multiplier: f64,
+}
+
+/// How a Fade finish places its gradient, from the finish's item definition. Every seed draws
+/// an x offset, a y offset and a rotation from these ranges in turn.
+#[derive(Deserialize, Debug)]
+struct FadeConfig {
+    rotation: (f64, f64),
+    /// Only fixed for most finishes, otherwise it scales the rotation
+    #[serde(default)]
+    offset_x: Option<(f64, f64)>,
+    /// Whether the gradient runs the other way, so the largest value is the fullest fade
+    #[serde(default)]
+    reversed: bool,
+    tiers: Vec<FadeTier>,
+}
+
+/// Fade percentage of every seed, worked out once when the table loads
+#[derive(Deserialize, Debug)]
+#[serde(from = "FadeConfig")]
+pub struct FadePattern {
+    percentages: Vec<f64>,
+    /// Most valuable first
+    tiers: Vec<FadeTier>,
+}
+
+impl From<FadeConfig> for FadePattern {
+    fn from(config: FadeConfig) -> Self {
+        let (x_start, x_end) = config.offset_x.unwrap_or((0.0, 0.0));
+
+        let raw: Vec<f64> = (0..SEEDS as i32)
+            .map(|seed| {
+                let mut random = UniformRandom::new(seed);
+                let x_offset = random.float(x_start, x_end);
+                random.float(0.0, 1.0);
+                let rotation = random.float(config.rotation.0, config.rotation.1);
+
+                if x_start != x_end { rotation * x_offset } else { rotation }
+            })
+            .collect();
+
+        let lowest = raw.iter().copied().fold(f64::INFINITY, f64::min);
+        let highest = raw.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let (best, worst) = if config.reversed { (highest, lowest) } else { (lowest, highest) };
+        let range = worst - best;
+
+        let percentages = raw
+            .iter()
+            .map(|value| if range == 0.0 { 100.0 } else { FADE_FLOOR + (worst - value) / range * (100.0 - FADE_FLOOR) })
+            .collect();
+
+        let mut tiers = config.tiers;
+        tiers.sort_by(|a, b| b.min_percentage.total_cmp(&a.min_percentage));
+
+        FadePattern { percentages, tiers }
+    }
+}
+
+impl FadePattern {
+    pub fn percentage(&self, seed: u32) -> Option<f64> {
+        self.percentages.get(seed as usize).copied()
+    }
+}
+
+/// Premiums of one skin, either by listed seeds or by how far its Fade reaches
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum SkinPatterns {
+    Seeds(Vec<PatternTier>),
+    Fade(FadePattern),
+}
+
+/// What a seed is worth compared to the market price of its skin
+#[derive(Debug, Clone, PartialEq)]
+pub struct Premium {
+    pub name: String,
+    pub multiplier: f64,
+}
+
+/// Tiers by skin name without exterior or StatTrak/Souvenir prefix, e.g. `AK-47 | Case Hardened`
+#[derive(Deserialize, Debug, Default)]
+pub struct PatternTable(HashMap<String, SkinPatterns>);
+
+/// Valve's `CUniformRandomStream`, which the game seeds with the paint seed to place a pattern
+struct UniformRandom {
+    idum: i32,
+    iy: i32,
+    iv: [i32; UniformRandom::NTAB],
+}
+
+impl UniformRandom {
+    const NTAB: usize = 32;
+    const IA: i32 = 16807;
+    const IM: i32 = 2147483647;
+    const IQ: i32 = 127773;
+    const IR: i32 = 2836;
+    const NDIV: i32 = 1 + (Self::IM - 1) / Self::NTAB as i32;
+    const AM: f64 = 1.0 / Self::IM as f64;
+    const RNMX: f64 = 1.0 - 1.2e-7;
+
+    fn new(seed: i32) -> Self {
+        UniformRandom {
+            idum: if seed < 0 { seed } else { -seed },
+            iy: 0,
+            iv: [0; Self::NTAB],
+        }
+    }
+
+    fn step(&mut self) {
+        let k = self.idum / Self::IQ;
+        self.idum = Self::IA * (self.idum - k * Self::IQ) - Self::IR * k;
+        if self.idum < 0 {
+            self.idum += Self::IM;
+        }
+    }
+
+    fn next(&mut self) -> i32 {
+        if self.idum <= 0 || self.iy == 0 {
+            self.idum = (-self.idum).max(1);
+            for j in (0..Self::NTAB + 8).rev() {
+                self.step();
+                if j < Self::NTAB {
+                    self.iv[j] = self.idum;
+                }
+            }
+            self.iy = self.iv[0];
+        }
+
+        self.step();
+        let j = (self.iy / Self::NDIV) as usize % Self::NTAB;
+        self.iy = self.iv[j];
+        self.iv[j] = self.idum;
+
+        self.iy
+    }
+
+    fn float(&mut self, low: f64, high: f64) -> f64 {
+        let float = (Self::AM * self.next() as f64).min(Self::RNMX);
+        float * (high - low) + low
+    }
+}
+
+/// The skin a market hash name is a copy of, e.g. `StatTrak™ AK-47 | Case Hardened (Field-Tested)`
+/// becomes `AK-47 | Case Hardened`
+pub fn pattern_skin(hash_name: &str) -> String {
+    let name = WEARS
+        .iter()
+        .find_map(|(wear, _, _)| hash_name.strip_suffix(&format!(" ({})", wear)))
+        .unwrap_or(hash_name);
+
+    variant_name(name, "")
+}
+
+impl PatternTable {
+    /// Whether any seed of this item carries a premium
+    pub fn covers(&self, hash_name: &str) -> bool {
+        self.0.contains_key(&pattern_skin(hash_name))
+    }
+
+    /// The most valuable tier `seed` reaches. Seed tiers are listed most valuable first,
+    /// Fade tiers name the percentage too.
+    pub fn lookup(&self, hash_name: &str, seed: u32) -> Option<Premium> {
+        match self.0.get(&pattern_skin(hash_name))? {
+            SkinPatterns::Seeds(tiers) => tiers
+                .iter()
+                .find(|tier| tier.seeds.contains(&seed))
+                .map(|tier| Premium { name: tier.name.clone(), multiplier: tier.multiplier }),
+            SkinPatterns::Fade(fade) => {
+                let percentage = fade.percentage(seed)?;
+                fade.tiers
+                    .iter()
+                    .find(|tier| percentage >= tier.min_percentage)
+                    .map(|tier| Premium {
+                        name: format!("{} ({:.1}% fade)", tier.name, percentage),
+                        multiplier: tier.multiplier,
+                    })
+            },
+        }
+    }
+
+    /// Fade percentage of `seed`, for skins listed with a Fade
+    pub fn fade(&self, hash_name: &str, seed: u32) -> Option<f64> {
+        match self.0.get(&pattern_skin(hash_name))? {
+            SkinPatterns::Fade(fade) => fade.percentage(seed),
+            SkinPatterns::Seeds(_) => None,
+        }
+    }
+}
+
+/// Reads `patterns.json`, without it every pattern is valued at the market price
+pub fn load_patterns(config: &Config) -> PatternTable {
+    let patterns = match fs::read_to_string(config.data_path(LOCAL_PATTERNS)) {
+        Ok(data) => match serde_json::from_str(&data) {
+            Ok(patterns) => patterns,
+            Err(e) => {
+                eprintln!("Failed to parse {}: {}", LOCAL_PATTERNS, e);
+                PatternTable::default()
+            },
+        },
+        Err(_) => {
+            println!("Could not find {}, valuing every pattern at the market price", LOCAL_PATTERNS);
+            PatternTable::default()
+        },
+    };
+
+    println!("Loaded pattern premiums for {} skins", patterns.0.len());
+
+    patterns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> PatternTable {
+        serde_json::from_str(include_str!("../../patterns.json")).unwrap()
+    }
+
+    #[test]
+    fn strips_exterior_and_variant() {
+        assert_eq!(pattern_skin("StatTrak™ AK-47 | Case Hardened (Field-Tested)"), "AK-47 | Case Hardened");
+        assert_eq!(pattern_skin("★ StatTrak™ Karambit | Fade (Factory New)"), "★ Karambit | Fade");
+        assert_eq!(pattern_skin("AK-47 | Case Hardened"), "AK-47 | Case Hardened");
+    }
+
+    #[test]
+    fn finds_listed_seeds() {
+        let table = table();
+
+        let premium = table.lookup("AK-47 | Case Hardened (Factory New)", 661).unwrap();
+        assert_eq!(premium, Premium { name: "Tier 1 blue gem".to_string(), multiplier: 20.0 });
+        assert!(table.lookup("StatTrak™ AK-47 | Case Hardened (Well-Worn)", 387).is_some());
+    }
+
+    #[test]
+    fn seed_without_premium() {
+        let table = table();
+
+        assert!(table.covers("AK-47 | Case Hardened (Factory New)"));
+        assert_eq!(table.lookup("AK-47 | Case Hardened (Factory New)", 1), None);
+        assert_eq!(table.fade("AK-47 | Case Hardened (Factory New)", 1), None);
+    }
+
+    #[test]
+    fn uncovered_skin() {
+        let table = table();
+
+        assert!(!table.covers("AWP | Asiimov (Field-Tested)"));
+        assert_eq!(table.lookup("AWP | Asiimov (Field-Tested)", 661), None);
+        assert_eq!(table.fade("AWP | Asiimov (Field-Tested)", 661), None);
+    }
+
+    #[test]
+    fn fade_spans_the_scale() {
+        let table = table();
+        let percentages: Vec<f64> = (0..=1000)
+            .map(|seed| table.fade("★ Karambit | Fade (Factory New)", seed).unwrap())
+            .collect();
+
+        assert!(percentages.iter().all(|percentage| (FADE_FLOOR..=100.0).contains(percentage)));
+        assert!(percentages.contains(&100.0));
+        assert!(percentages.contains(&FADE_FLOOR));
+        assert_eq!(table.fade("★ Karambit | Fade (Factory New)", 1001), None);
+    }
+
+    #[test]
+    fn fade_premium_follows_percentage() {
+        let table = table();
+        let name = "Glock-18 | Fade (Factory New)";
+        let fullest = (0..=1000).find(|&seed| table.fade(name, seed) == Some(100.0)).unwrap();
+        let faintest = (0..=1000).find(|&seed| table.fade(name, seed) == Some(FADE_FLOOR)).unwrap();
+
+        let premium = table.lookup(name, fullest).unwrap();
+        assert_eq!(premium.name, "Full fade (100.0% fade)");
+        assert_eq!(premium.multiplier, 1.3);
+        assert_eq!(table.lookup(name, faintest), None);
+    }
+
+    #[test]
+    fn reversed_fade_flips_the_scale() {
+        let config = |reversed| FadeConfig { rotation: (-55.0, -65.0), offset_x: None, reversed, tiers: vec![] };
+        let forward = FadePattern::from(config(false));
+        let reversed = FadePattern::from(config(true));
+
+        for seed in [0, 412, 1000] {
+            let sum = forward.percentage(seed).unwrap() + reversed.percentage(seed).unwrap();
+            assert!((sum - (100.0 + FADE_FLOOR)).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn random_stream_is_seeded() {
+        let draws = |seed| {
+            let mut random = UniformRandom::new(seed);
+            [random.float(0.0, 1.0), random.float(0.0, 1.0)]
+        };
+
+        assert_eq!(draws(412), draws(412));
+        assert_ne!(draws(412), draws(413));
+        assert!(draws(0).iter().all(|draw| (0.0..1.0).contains(draw)));
+    }
+}
@@ -1,255 +1,420 @@
-use std::collections::HashMap;
-use std::fs::{self, File};
-use std::io::copy;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::Read;
+use std::sync::Arc;
 
 use flate2::read::GzDecoder;
-use serde::Deserialize;
+use serde::de::{DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
 
 use crate::items::*;
+use crate::cache::{blocking, conditional_get, load_or_refresh, read_with_rollback, CacheMeta};
+use crate::confidence::Confidence;
+use crate::doppler::{load_doppler, phased_name};
+use crate::sources::{registry, PriceSource, SourceId, SteamWindows};
+use crate::config::Config;
+use crate::lenient::Lenient;
+use crate::strategy::Strategy;
+use crate::trend::SteamTrend;
+use crate::Error;
+
+/// A single prices_v6.json entry, keyed by market name
+pub type TItem = HashMap<String, Value>;
+
+/// Consolidated prices keyed by (phased) market hash name
+pub type PricedItems = HashMap<Arc<str>, Priced>;
+
+/// Catalog items grouped by market hash name, Doppler finishes have one entry per phase
+type Catalog = HashMap<String, Vec<Arc<Item>>>;
+
+/// One market's price for an item
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct Quote {
+    pub price: f64,
+    pub weight: f32,
+    source: SourceId,
+}
 
-type Doppler = HashMap<String, Option<f64>>;
+impl Quote {
+    #[cfg(test)]
+    pub fn new(price: f64, weight: f32, source: SourceId) -> Self {
+        Quote { price, weight, source }
+    }
 
-// steam
-#[derive(Deserialize, Debug)]
-pub struct SItem {
-    last_24h: Option<f64>,
-    last_7d: Option<f64>,
-    last_30d: Option<f64>,
-    last_90d: Option<f64>
-}
+    fn source(&self) -> &'static dyn PriceSource {
+        registry()[self.source as usize].as_ref()
+    }
 
-// skinport
-#[derive(Deserialize, Debug)]
-pub struct PItem {
-    starting_at: Option<f64>
-}
+    /// Key of the market in the feed
+    pub fn name(&self) -> &'static str {
+        self.source().name()
+    }
 
-// cstrade
-#[derive(Deserialize, Debug)]
-pub struct CItem {
-    price: Option<f64>,
-    doppler: Option<Doppler>
-}
+    pub fn label(&self) -> &'static str {
+        self.source().label()
+    }
 
-// buff
-#[derive(Deserialize, Debug)]
-pub struct BPrice {
-    price: Option<f64>,
-    doppler: Option<Doppler>
+    pub fn reputable(&self) -> bool {
+        self.source().reputable()
+    }
 }
 
-#[derive(Deserialize, Debug)]
-pub struct BItem {
-    starting_at: Option<BPrice>
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Priced {
+    /// Shared with the other phases of the same finish
+    pub info: Arc<Item>,
+    pub quotes: Box<[Quote]>,
+    /// Quotes rejected as outliers before aggregation
+    pub dropped: Box<[Quote]>,
+    pub confidence: Confidence,
+    /// Steam sale averages, absent for Doppler phases which Steam does not list separately
+    pub steam_windows: Option<SteamWindows>,
+    /// Base price of a phased finish, used when the actual phase is unknown
+    pub phase_fallback: bool,
+
+    pub feather: Option<f64>,
 }
 
+pub const LOCAL_PRICES: &str = "prices.json";
+const API_PRICES: &str = "https://prices.csgotrader.app/latest/prices_v6.json";
 
-#[derive(Deserialize, Debug)]
-pub struct TItem {
-    steam: SItem,
-    skinport: Option<PItem>,
-    cstrade: Option<CItem>,
-    buff163: BItem,
+/// Sanity floor for a downloaded price feed, the real one prices tens of thousands of items
+const MIN_PRICES: usize = 1000;
 
-    lootfarm: Option<f64>,
-    csgoempire: Option<f64>,
-    swapgg: Option<f64>,
-    skinwallet: Option<f64>,
+async fn load_prices(path: &str, catalog: &Arc<Catalog>, config: &Config) -> Result<PricedItems, Error> {
+    let (path, catalog, config) = (path.to_string(), catalog.clone(), config.clone());
+    blocking(move || read_with_rollback(&path, |bytes| consolidate(bytes, &catalog, &config))).await
 }
 
-#[derive(Debug)]
-pub struct Priced {
-    pub info: Item,
-    
-    pub feather: Option<f64>,
-    pub steam: Option<f64>,
-    pub skinport: Option<f64>,
-    pub buff: Option<f64>
-}
+async fn refresh_prices(path: &str, catalog: &Arc<Catalog>, config: &Config) -> Result<PricedItems, Error> {
+    let fetched = match conditional_get(API_PRICES, path).await? {
+        Some(fetched) => fetched,
+        None => match load_prices(path, catalog, config).await {
+            Ok(priced_items) => return Ok(priced_items),
+            Err(e) => {
+                // The copy the validators vouch for is gone or broken, only a full download helps
+                eprintln!("{} was not modified upstream but could not be loaded, downloading it again: {}", path, e);
+                CacheMeta::forget(path);
+                conditional_get(API_PRICES, path).await?.ok_or("Price feed answered 304 without validators")?
+            },
+        },
+    };
+
+    let (path, catalog, config) = (path.to_string(), catalog.clone(), config.clone());
+    blocking(move || {
+        let mut json = Vec::new();
+        GzDecoder::new(&fetched.body[..]).read_to_end(&mut json)?;
 
-const LOCAL_DOPPLER: &str = "doppler.json";
-async fn load_doppler() -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
-    let data = fs::read_to_string(LOCAL_DOPPLER)?;
-    let dopplers: HashMap<String, String> = serde_json::from_str(&data)?;
-    Ok(dopplers)
+        // Only the shape and size are checked here, entries are priced while streaming below
+        fetched.commit(&path, &json, MIN_PRICES, HashMap::<String, IgnoredAny>::len)?;
+        consolidate(&json, &catalog, &config)
+    }).await
 }
 
-const LOCAL_PRICES: &str = "prices.json";
-const API_PRICES: &str = "https://prices.csgotrader.app/latest/prices_v6.json";
+impl Priced {
+    fn unpriced(item: &Arc<Item>) -> Self {
+        Priced {
+            info: item.clone(),
+            quotes: Box::new([]),
+            dropped: Box::new([]),
+            confidence: Confidence::default(),
+            steam_windows: None,
+            phase_fallback: false,
+            feather: None,
+        }
+    }
 
-async fn load_prices() -> Result<HashMap<String, TItem>, Box<dyn std::error::Error>> {
-    let data = fs::read_to_string(LOCAL_PRICES)?;
-    let items: HashMap<String, TItem> = serde_json::from_str(&data)?;
-    Ok(items)
-}
+    /// Suggested price, recomputed from the quotes when a strategy other than the configured default is given
+    pub fn suggested(&self, strategy: Option<&Strategy>) -> Option<f64> {
+        match strategy {
+            Some(strategy) => strategy.aggregate(&self.quotes),
+            None => self.feather,
+        }
+    }
 
-pub async fn refresh_prices() -> Result<HashMap<String, TItem>, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-    let response = client.get(API_PRICES).send().await?;
+    pub fn steam_trend(&self) -> Option<SteamTrend> {
+        self.steam_windows.as_ref().map(SteamTrend::compute)
+    }
+
+    /// What a market lists the item for, even when it is left out of the suggested price
+    pub fn market(&self, name: &str) -> Option<f64> {
+        self.quotes
+            .iter()
+            .chain(self.dropped.iter())
+            .find(|quote| quote.name() == name)
+            .map(|quote| quote.price)
+    }
+
+    pub fn steam(&self) -> Option<f64> {
+        self.market("steam")
+    }
+
+    pub fn skinport(&self) -> Option<f64> {
+        self.market("skinport")
+    }
 
-    if response.status().is_success() {
-        let bytes = response.bytes().await?;
-        let mut gz = GzDecoder::new(&bytes[..]);
-        let mut file = File::create(LOCAL_PRICES)?;
-    
-        copy(&mut gz, &mut file)?;
+    pub fn buff(&self) -> Option<f64> {
+        self.market("buff163")
     }
 
-    load_prices().await
+    /// Every market quote cheapest first, paired with whether it was rejected as an outlier
+    pub fn markets(&self) -> Vec<(&Quote, bool)> {
+        let mut markets: Vec<(&Quote, bool)> = self.quotes
+            .iter()
+            .map(|quote| (quote, false))
+            .chain(self.dropped.iter().map(|quote| (quote, true)))
+            .collect();
+
+        markets.sort_by(|(a, _), (b, _)| a.price.partial_cmp(&b.price).unwrap_or(std::cmp::Ordering::Equal));
+        markets
+    }
+}
+
+fn price_item(item: &Arc<Item>, item_price: &TItem, config: &Config) -> Priced {
+    let phase = item.phase.as_deref();
+
+    let quotes: Vec<Quote> = registry()
+        .iter()
+        .enumerate()
+        .filter_map(|(id, source)| {
+            item_price.get(source.name())
+                .and_then(|market| source.price(market, phase))
+                .filter(|&price| price > 0.0)
+                .map(|price| Quote {
+                    price,
+                    weight: config.source_weights.get(source.name()).copied().unwrap_or(source.weight()) as f32,
+                    source: id as SourceId,
+                })
+        })
+        .collect();
+
+    let (quotes, dropped) = config.outlier_filter.apply(quotes);
+
+    let steam_windows = item_price.get("steam")
+        .filter(|_| phase.is_none())
+        .and_then(SteamWindows::parse);
+    let confidence = Confidence::compute(&quotes, &dropped, steam_windows.as_ref());
+
+    Priced {
+        info: item.clone(),
+        feather: config.price_strategy.aggregate(&quotes),
+        quotes: quotes.into_boxed_slice(),
+        dropped: dropped.into_boxed_slice(),
+        confidence,
+        steam_windows,
+        phase_fallback: false,
+    }
 }
 
-fn power_mean(prices: &[f64]) -> Option<f64> {
-    const POWER: f64 = -3.0;
+/// Groups the catalog by market hash name so feed entries can be matched as they stream in
+fn index_catalog(item_info: HashMap<String, Item>) -> Catalog {
+    let mut catalog: Catalog = HashMap::new();
 
-    if prices.is_empty() {
-        return None;
+    for item in item_info.into_values() {
+        if let Some(hash_name) = &item.market_hash_name {
+            catalog.entry(hash_name.clone()).or_default().push(Arc::new(item));
+        }
     }
 
-    let n = prices.len() as f64;
-    let sum: f64 = prices.iter().map(|&p| p.powf(POWER)).sum();
+    catalog
+}
 
-    Some((sum / n).powf(1.0 / POWER))
+/// Running state while the price feed is parsed one entry at a time
+struct Consolidation<'a> {
+    catalog: &'a Catalog,
+    config: &'a Config,
+    priced_items: PricedItems,
+    /// Catalog names that had a feed entry
+    seen: HashSet<&'a str>,
+    /// Listed and priced counts per market, indexed like the registry
+    coverage: Vec<(usize, usize)>,
+    unknown_markets: HashSet<String>,
+    malformed: usize,
+    success_count: usize,
 }
 
-pub async fn consolidate_prices() -> Result<(HashMap<String, Priced>, HashMap<String, String>), Box<dyn std::error::Error>> {
-    let item_info = match scrape_items().await {
-        Ok(items) => items,
-        Err(e) => {
-            return Err(e);
+impl<'a> Consolidation<'a> {
+    fn new(catalog: &'a Catalog, config: &'a Config) -> Self {
+        Consolidation {
+            catalog,
+            config,
+            priced_items: HashMap::new(),
+            seen: HashSet::new(),
+            coverage: vec![(0, 0); registry().len()],
+            unknown_markets: HashSet::new(),
+            malformed: 0,
+            success_count: 0,
         }
-    };
+    }
 
-    let item_prices: HashMap<String, TItem> = match load_prices().await {
-        Ok(items) => {
-            println!("Loaded local {}", LOCAL_PRICES);
-            items
-        },
-        Err(_) => {
-            println!("Could not find {}", LOCAL_PRICES);
-            match refresh_prices().await {
-                Ok(items) => {
-                    println!("Wrote new {}", LOCAL_PRICES);
-                    items
-                },
-                Err(e) => {
-                    println!("Failed to fetch prices");
-                    return Err(e);
+    fn insert(&mut self, hash_name: &str, item: &Arc<Item>, priced: Priced) {
+        let key: Arc<str> = match &item.phase {
+            Some(doppler_phase) => phased_name(hash_name, doppler_phase).into(),
+            None => hash_name.into(),
+        };
+        self.priced_items.insert(key, priced);
+    }
+
+    /// Records how many listings each market could be priced from, and which markets are not understood
+    fn cover(&mut self, item_price: &TItem) {
+        for (source, (listed, priced)) in registry().iter().zip(self.coverage.iter_mut()) {
+            if let Some(market) = item_price.get(source.name()).filter(|market| !market.is_null()) {
+                *listed += 1;
+                if source.price(market, None).is_some() {
+                    *priced += 1;
                 }
             }
         }
-    };
 
-    let doppler_data = load_doppler().await.expect("Failed to load doppler data");
-    println!("Loaded doppler data");
+        for market in item_price.keys() {
+            if !registry().iter().any(|source| source.name() == market) {
+                self.unknown_markets.insert(market.clone());
+            }
+        }
+    }
 
-    let mut priced_items: HashMap<String, Priced> = HashMap::new();
-    let mut success_count = 0;
+    fn add(&mut self, hash_name: &str, item_price: &TItem) {
+        self.cover(item_price);
 
-    for (_key, item) in &item_info {
-        if let Some(hash_name) = &item.market_hash_name {
-            priced_items.insert(
-                if let Some(doppler_phase) = item.phase.clone() {
-                    hash_name.clone() + " " + &doppler_phase
-                } else {
-                    hash_name.clone()
-                },
-
-                if let Some(item_price) = item_prices.get(hash_name) {
-                    success_count += 1;
-
-                    if let Some(doppler_phase) = item.phase.clone() {
-                        let trader_price = item_price.cstrade
-                            .as_ref()
-                            .and_then(|cstrade| cstrade.doppler.as_ref())
-                            .and_then(|doppler| doppler.get(&doppler_phase))
-                            .and_then(|doppler_option| *doppler_option);
-
-                        let buff_price = item_price.buff163.starting_at
-                            .as_ref()
-                            .and_then(|starting_at| starting_at.doppler.as_ref())
-                            .and_then(|doppler| doppler.get(&doppler_phase))
-                            .and_then(|doppler_option| *doppler_option);
-
-                        Priced {
-                            info: item.clone(),
-                            feather: {
-                                let prices: Vec<f64> = [
-                                    trader_price,
-                                    buff_price
-                                ]
-                                .iter()
-                                .filter_map(|&price| price)
-                                .collect();
-
-                                power_mean(&prices) 
-                            },
-                            steam: None,
-                            skinport: None,
-                            buff: buff_price,
-                        }
-                    } else {
-                        let mut steam_price = item_price.steam.last_24h
-                            .or(item_price.steam.last_7d)
-                            .or(item_price.steam.last_30d)
-                            .or(item_price.steam.last_90d);
-                        
-                        if steam_price == Some(0.0) {
-                            steam_price = None
-                        }
-
-                        let trader_price = item_price.cstrade
-                            .as_ref()
-                            .and_then(|cstrade| cstrade.price);
-                        let skinport_price = item_price.skinport
-                            .as_ref()
-                            .and_then(|skinport| skinport.starting_at);
-                        let buff_price = item_price.buff163.starting_at
-                            .as_ref()
-                            .and_then(|starting_at| starting_at.price);
-
-                        Priced {
-                            info: item.clone(),
-                            feather: {
-                                let mut prices: Vec<f64> = [
-                                    steam_price,
-                                    trader_price,
-                                    skinport_price,
-                                    buff_price,
-                                    item_price.lootfarm,
-                                    item_price.csgoempire,
-                                    item_price.swapgg,
-                                    item_price.skinwallet,
-                                ]
-                                .iter()
-                                .filter_map(|&price| price)
-                                .collect();
-                                
-                                prices.retain(|&price| price != 0.0);
-
-                                power_mean(&prices) 
-                            },
-                            steam: steam_price,
-                            skinport: skinport_price,
-                            buff: buff_price,
-                        }
-                    }
-
-                } else {
-                    Priced {
-                        info: item.clone(),
-                        feather: None,
-                        steam: None,
-                        skinport: None,
-                        buff: None,
-                    }
-                }
+        let Some((hash_name, items)) = self.catalog.get_key_value(hash_name) else {
+            return;
+        };
+        self.seen.insert(hash_name);
 
-            );
+        for item in items {
+            self.success_count += 1;
+            let priced = price_item(item, item_price, self.config);
+            self.insert(hash_name, item, priced);
         }
 
+        // Phases we can't identify (new gems, unknown icons) fall back to the price across all phases
+        if let Some(base_item) = phase_fallback_base(items) {
+            let mut priced = price_item(&base_item, item_price, self.config);
+            priced.phase_fallback = true;
+            self.insert(hash_name, &base_item, priced);
+        }
     }
 
-    println!("Processed {}/{} items", success_count, priced_items.len());
+    fn finish(mut self) -> PricedItems {
+        for (hash_name, items) in self.catalog {
+            if self.seen.contains(hash_name.as_str()) {
+                continue;
+            }
+
+            for item in items {
+                self.insert(hash_name, item, Priced::unpriced(item));
+            }
+
+            if let Some(base_item) = phase_fallback_base(items) {
+                let mut priced = Priced::unpriced(&base_item);
+                priced.phase_fallback = true;
+                self.insert(hash_name, &base_item, priced);
+            }
+        }
+
+        for (source, (listed, priced)) in registry().iter().zip(&self.coverage) {
+            println!("{}: priced {}/{} listings", source.name(), priced, listed);
+        }
+        if !self.unknown_markets.is_empty() {
+            println!("Ignoring unknown markets: {:?}", self.unknown_markets);
+        }
+        if self.malformed > 0 {
+            eprintln!("Skipped {} malformed entries in {}", self.malformed, LOCAL_PRICES);
+        }
+
+        println!("Processed {}/{} items", self.success_count, self.priced_items.len());
+
+        let mut dropped_by_source: HashMap<&str, usize> = HashMap::new();
+        for quote in self.priced_items.values().flat_map(|priced| priced.dropped.iter()) {
+            *dropped_by_source.entry(quote.name()).or_default() += 1;
+        }
+        if !dropped_by_source.is_empty() {
+            println!("Dropped outlier quotes: {:?}", dropped_by_source);
+        }
+
+        self.priced_items.shrink_to_fit();
+        self.priced_items
+    }
+}
+
+/// Phase-less copy of a Doppler finish, unless the catalog already lists one
+fn phase_fallback_base(items: &[Arc<Item>]) -> Option<Arc<Item>> {
+    if items.iter().any(|item| item.phase.is_none()) {
+        return None;
+    }
+
+    let phased_item = items.first()?;
+    Some(Arc::new(Item {
+        phase: None,
+        ..(**phased_item).clone()
+    }))
+}
 
-    return Ok((priced_items, doppler_data));
-}
\ No newline at end of file
+/// Feeds each entry of the price feed map into a [`Consolidation`] without building the whole map
+struct FeedVisitor<'a, 'b>(&'b mut Consolidation<'a>);
+
+impl<'de> DeserializeSeed<'de> for FeedVisitor<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FeedVisitor<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a map of market hash names to market prices")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(hash_name) = map.next_key::<String>()? {
+            match map.next_value::<Lenient<TItem>>()? {
+                Lenient(Some(item_price)) => self.0.add(&hash_name, &item_price),
+                Lenient(None) => self.0.malformed += 1,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Prices every catalog item while streaming through the raw price feed
+fn consolidate(feed: &[u8], catalog: &Catalog, config: &Config) -> Result<PricedItems, Error> {
+    let mut consolidation = Consolidation::new(catalog, config);
+
+    let mut deserializer = serde_json::Deserializer::from_slice(feed);
+    FeedVisitor(&mut consolidation).deserialize(&mut deserializer)?;
+    deserializer.end()?;
+
+    Ok(consolidation.finish())
+}
+
+pub async fn consolidate_prices(config: &Config) -> Result<(PricedItems, HashMap<String, String>), Error> {
+    let item_info = scrape_items(config).await?;
+    let doppler_data = load_doppler(&item_info, config).await?;
+    let catalog = Arc::new(index_catalog(item_info));
+
+    let path = config.data_path(LOCAL_PRICES);
+    let priced_items = load_or_refresh(
+        &path,
+        config,
+        load_prices(&path, &catalog, config),
+        refresh_prices(&path, &catalog, config),
+    ).await?;
+
+    Ok((priced_items, doppler_data))
+}
+
+/// Re-downloads the item catalog and price feed, ignoring any local copies
+pub async fn refresh_consolidated(config: &Config) -> Result<(PricedItems, HashMap<String, String>), Error> {
+    let item_info = refresh_json(&config.data_path(LOCAL_FILE)).await?;
+    let doppler_data = load_doppler(&item_info, config).await?;
+    let catalog = Arc::new(index_catalog(item_info));
+
+    let priced_items = refresh_prices(&config.data_path(LOCAL_PRICES), &catalog, config).await?;
+    println!("Refreshed item catalog and {}", LOCAL_PRICES);
+
+    Ok((priced_items, doppler_data))
+}
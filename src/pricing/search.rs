@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::priced_items::PricedItems;
+use crate::Error;
+
+/// How well one query word matched a word of an item name
+const EXACT_MATCH: f32 = 3.0;
+const PREFIX_MATCH: f32 = 2.0;
+const INNER_MATCH: f32 = 1.0;
+
+/// Weight of how much of the item name the query covers, relative to a single match
+const COVERAGE_WEIGHT: f32 = 4.0;
+
+/// Weight of market listings and price, scaled to at most this so it breaks ties
+/// but never beats a better match
+const POPULARITY_WEIGHT: f32 = 0.9;
+
+/// Most "did you mean" suggestions offered for a name that could not be resolved
+const SUGGESTIONS: usize = 5;
+
+/// Shorthands people type for exteriors and variants
+const ABBREVIATIONS: [(&str, &str); 7] = [
+    ("fn", "factory new"),
+    ("mw", "minimal wear"),
+    ("ft", "field tested"),
+    ("ww", "well worn"),
+    ("bs", "battle scarred"),
+    ("st", "stattrak"),
+    ("sv", "souvenir"),
+];
+
+/// Lowercase words of an item name or query, split the same way for both
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// A name reduced to lowercase words with abbreviations expanded, so case, `★`, `™`
+/// and punctuation don't matter: `StatTrak™ AK-47 | Redline (FT)` is `stattrak ak 47 redline field tested`
+fn normalize(text: &str) -> String {
+    words(text)
+        .map(|word| match ABBREVIATIONS.iter().find(|(short, _)| *short == word) {
+            Some((_, long)) => long.to_string(),
+            None => word,
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Levenshtein distance in characters, or `None` as soon as it must exceed `max`
+fn edit_distance(a: &[char], b: &str, max: usize) -> Option<usize> {
+    let b_len = b.chars().count();
+    if a.len().abs_diff(b_len) > max {
+        return None;
+    }
+
+    let mut previous: Vec<usize> = (0..=b_len).collect();
+    let mut current = vec![0; b_len + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.chars().enumerate() {
+            let substitution = previous[j] + usize::from(*ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        if current.iter().min().is_some_and(|&row_min| row_min > max) {
+            return None;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    Some(previous[b_len]).filter(|&distance| distance <= max)
+}
+
+/// Outcome of resolving a typed name against the item table
+pub enum Lookup<'a> {
+    Found(&'a str),
+    /// Closest names, best first, empty when nothing came close
+    Suggestions(Vec<&'a str>),
+}
+
+/// Word index over the item table for autocomplete, built once per store so keystrokes
+/// only binary search a sorted word list instead of scanning every name
+#[derive(Serialize, Deserialize)]
+pub struct SearchIndex {
+    /// Left out of snapshots, `attach` shares them with the restored item table instead
+    #[serde(skip)]
+    names: Vec<Arc<str>>,
+    /// `normalize`d names, to the first item with each
+    normalized: HashMap<Box<str>, u32>,
+    /// Every distinct word, sorted, with the items containing it
+    tokens: Vec<(Box<str>, Vec<u32>)>,
+    /// Per item, in the order of `names`
+    word_counts: Vec<u8>,
+    popularity: Vec<f32>,
+    /// Most popular first, answers an empty query
+    by_popularity: Vec<u32>,
+}
+
+impl SearchIndex {
+    pub fn build(mut names: Vec<Arc<str>>, item_data: &PricedItems) -> Self {
+        // Alphabetical, so equally ranked matches come out in a stable order
+        names.sort_unstable();
+
+        let mut postings: HashMap<String, Vec<u32>> = HashMap::new();
+        let mut word_counts = Vec::with_capacity(names.len());
+        let mut popularity = Vec::with_capacity(names.len());
+        let mut normalized = HashMap::with_capacity(names.len());
+
+        for (id, name) in names.iter().enumerate() {
+            let mut count = 0u8;
+            for word in words(name) {
+                count = count.saturating_add(1);
+                let items = postings.entry(word).or_default();
+                if items.last() != Some(&(id as u32)) {
+                    items.push(id as u32);
+                }
+            }
+            word_counts.push(count.max(1));
+
+            normalized.entry(normalize(name).into_boxed_str()).or_insert(id as u32);
+
+            // Listed on more markets and pricier items are what people look up most
+            popularity.push(item_data.get(name).map_or(0.0, |priced| {
+                priced.quotes.len() as f32 + (1.0 + priced.feather.unwrap_or(0.0) as f32).ln()
+            }));
+        }
+
+        let most_popular = popularity.iter().copied().fold(0.0f32, f32::max);
+        if most_popular > 0.0 {
+            for score in &mut popularity {
+                *score /= most_popular;
+            }
+        }
+
+        let mut tokens: Vec<(Box<str>, Vec<u32>)> = postings
+            .into_iter()
+            .map(|(word, items)| (word.into_boxed_str(), items))
+            .collect();
+        tokens.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        let mut by_popularity: Vec<u32> = (0..names.len() as u32).collect();
+        by_popularity.sort_by(|&a, &b| popularity[b as usize].total_cmp(&popularity[a as usize]));
+
+        SearchIndex {
+            names,
+            normalized,
+            tokens,
+            word_counts,
+            popularity,
+            by_popularity,
+        }
+    }
+
+    /// Gives an index read from a snapshot its names, the keys of the table it was built over
+    pub fn attach(&mut self, item_data: &PricedItems) -> Result<(), Error> {
+        if item_data.len() != self.word_counts.len() {
+            return Err(format!("Search index covers {} items, the table has {}", self.word_counts.len(), item_data.len()).into());
+        }
+
+        let mut names: Vec<Arc<str>> = item_data.keys().cloned().collect();
+        names.sort_unstable();
+        self.names = names;
+
+        Ok(())
+    }
+
+    /// Best match quality per item for one query word. Words starting with it are found by
+    /// binary search, words merely containing it only by a scan of the distinct words.
+    fn matches(&self, word: &str, inner: bool) -> HashMap<u32, f32> {
+        let mut found: HashMap<u32, f32> = HashMap::new();
+        let mut add = |items: &[u32], quality: f32| {
+            for &id in items {
+                let best = found.entry(id).or_insert(quality);
+                *best = best.max(quality);
+            }
+        };
+
+        let start = self.tokens.partition_point(|(token, _)| &**token < word);
+        for (token, items) in self.tokens[start..].iter().take_while(|(token, _)| token.starts_with(word)) {
+            add(items, if &**token == word { EXACT_MATCH } else { PREFIX_MATCH });
+        }
+
+        if inner {
+            for (token, items) in &self.tokens {
+                if !token.starts_with(word) && token.contains(word) {
+                    add(items, INNER_MATCH);
+                }
+            }
+        }
+
+        found
+    }
+
+    /// Items matching every word of `query` and passing `keep`, best first. Ranked by how
+    /// well each word matched, how much of the name the query covers, then popularity.
+    pub fn search(&self, query: &str, limit: usize, keep: impl Fn(&str) -> bool) -> Vec<&str> {
+        let query_words: Vec<String> = words(query).collect();
+
+        if query_words.is_empty() {
+            return self.by_popularity
+                .iter()
+                .map(|&id| &*self.names[id as usize])
+                .filter(|name| keep(name))
+                .take(limit)
+                .collect();
+        }
+
+        let mut scored = self.scored(&query_words, false, &keep);
+        // Words in the middle of item words are slower to find, only look when prefixes fall short
+        if scored.len() < limit {
+            scored = self.scored(&query_words, true, &keep);
+        }
+
+        let by_score = |a: &(u32, f32), b: &(u32, f32)| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0));
+        if scored.len() > limit {
+            scored.select_nth_unstable_by(limit, by_score);
+            scored.truncate(limit);
+        }
+        scored.sort_unstable_by(by_score);
+
+        scored
+            .into_iter()
+            .map(|(id, _)| &*self.names[id as usize])
+            .collect()
+    }
+
+    /// Resolves free text to an item name: after normalizing, or when a single name is one typo
+    /// away. Anything else gets the closest names by edit distance, or failing that by words.
+    /// Exact names are cheaper to check against the item table first.
+    pub fn lookup(&self, query: &str) -> Lookup<'_> {
+        let plain = normalize(query);
+        if let Some(&id) = self.normalized.get(plain.as_str()) {
+            return Lookup::Found(&self.names[id as usize]);
+        }
+
+        // Longer names tolerate more typos
+        let plain: Vec<char> = plain.chars().collect();
+        let max_distance = (1 + plain.len() / 10).min(3);
+
+        let mut close: Vec<(usize, u32)> = self.normalized
+            .iter()
+            .filter_map(|(name, &id)| Some((edit_distance(&plain, name, max_distance)?, id)))
+            .collect();
+        close.sort_by(|a, b| a.0
+            .cmp(&b.0)
+            .then(self.popularity[b.1 as usize].total_cmp(&self.popularity[a.1 as usize]))
+            .then(a.1.cmp(&b.1)));
+
+        match close.as_slice() {
+            [(1, id)] => return Lookup::Found(&self.names[*id as usize]),
+            [(1, id), (second, _), ..] if *second > 1 => return Lookup::Found(&self.names[*id as usize]),
+            _ => {},
+        }
+
+        if close.is_empty() {
+            return Lookup::Suggestions(self.search(query, SUGGESTIONS, |_| true));
+        }
+
+        Lookup::Suggestions(close
+            .into_iter()
+            .take(SUGGESTIONS)
+            .map(|(_, id)| &*self.names[id as usize])
+            .collect())
+    }
+
+    fn scored(&self, query_words: &[String], inner: bool, keep: &impl Fn(&str) -> bool) -> Vec<(u32, f32)> {
+        let mut candidates: Option<HashMap<u32, f32>> = None;
+
+        for word in query_words {
+            let found = self.matches(word, inner);
+            candidates = Some(match candidates {
+                None => found,
+                Some(mut candidates) => {
+                    candidates.retain(|id, quality| match found.get(id) {
+                        Some(more) => {
+                            *quality += more;
+                            true
+                        },
+                        None => false,
+                    });
+                    candidates
+                },
+            });
+
+            if candidates.as_ref().is_some_and(HashMap::is_empty) {
+                break;
+            }
+        }
+
+        candidates
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|&(id, _)| keep(&self.names[id as usize]))
+            .map(|(id, quality)| {
+                let coverage = (query_words.len() as f32 / self.word_counts[id as usize] as f32).min(1.0);
+                (id, quality + coverage * COVERAGE_WEIGHT + self.popularity[id as usize] * POPULARITY_WEIGHT)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(names: &[&str]) -> SearchIndex {
+        SearchIndex::build(names.iter().map(|&name| Arc::from(name)).collect(), &PricedItems::new())
+    }
+
+    fn distance(a: &str, b: &str, max: usize) -> Option<usize> {
+        edit_distance(&a.chars().collect::<Vec<char>>(), b, max)
+    }
+
+    #[test]
+    fn normalize_ignores_symbols_and_expands_abbreviations() {
+        assert_eq!(normalize("StatTrak™ AK-47 | Redline (Field-Tested)"), "stattrak ak 47 redline field tested");
+        assert_eq!(normalize("st ak-47 redline ft"), "stattrak ak 47 redline field tested");
+        assert_eq!(normalize("★ Karambit | Fade (FN)"), "karambit fade factory new");
+    }
+
+    #[test]
+    fn edit_distance_counts_characters() {
+        assert_eq!(distance("redline", "redline", 2), Some(0));
+        assert_eq!(distance("redlnie", "redline", 2), Some(2));
+        assert_eq!(distance("redlin", "redline", 2), Some(1));
+        assert_eq!(distance("ak 47", "ak 74", 1), None);
+        assert_eq!(distance("asiimov", "redline", 3), None);
+    }
+
+    fn ranking_index() -> SearchIndex {
+        index(&[
+            "AK-47 | Redline (Field-Tested)",
+            "AWP | Asiimov (Field-Tested)",
+            "M4A4 | Red DDPAT (Field-Tested)",
+            "Sticker | Hundred Thieves | 2020 RMR",
+        ])
+    }
+
+    #[test]
+    fn ranks_exact_then_prefix_then_inner_matches() {
+        assert_eq!(ranking_index().search("red", 5, |_| true), vec![
+            "M4A4 | Red DDPAT (Field-Tested)",
+            "AK-47 | Redline (Field-Tested)",
+            "Sticker | Hundred Thieves | 2020 RMR",
+        ]);
+    }
+
+    #[test]
+    fn search_stops_at_the_limit() {
+        let index = ranking_index();
+
+        assert_eq!(index.search("red", 2, |_| true), vec!["M4A4 | Red DDPAT (Field-Tested)", "AK-47 | Redline (Field-Tested)"]);
+        assert_eq!(index.search("red", 1, |_| true), vec!["M4A4 | Red DDPAT (Field-Tested)"]);
+        assert_eq!(index.search("", 3, |_| true).len(), 3);
+        assert!(index.search("red", 0, |_| true).is_empty());
+    }
+
+    #[test]
+    fn search_needs_every_word_and_keep() {
+        let index = ranking_index();
+
+        assert_eq!(index.search("ak red", 5, |_| true), vec!["AK-47 | Redline (Field-Tested)"]);
+        assert_eq!(index.search("red", 5, |name| !name.starts_with("M4A4")), vec![
+            "AK-47 | Redline (Field-Tested)",
+            "Sticker | Hundred Thieves | 2020 RMR",
+        ]);
+        assert!(index.search("howl", 5, |_| true).is_empty());
+    }
+
+    #[test]
+    fn lookup_resolves_normalized_names_and_typos() {
+        let index = index(&[
+            "AK-47 | Redline (Field-Tested)",
+            "StatTrak™ AK-47 | Redline (Field-Tested)",
+            "AWP | Asiimov (Field-Tested)",
+        ]);
+
+        assert!(matches!(index.lookup("st ak-47 redline ft"), Lookup::Found("StatTrak™ AK-47 | Redline (Field-Tested)")));
+        assert!(matches!(index.lookup("awp asimov ft"), Lookup::Found("AWP | Asiimov (Field-Tested)")));
+    }
+
+    #[test]
+    fn lookup_suggests_when_ambiguous() {
+        let index = index(&[
+            "Sticker | Titan | Katowice 2014",
+            "Sticker | Titan (Holo) | Katowice 2014",
+        ]);
+
+        let Lookup::Suggestions(suggestions) = index.lookup("titan") else {
+            panic!("expected suggestions");
+        };
+        assert_eq!(suggestions.len(), 2);
+    }
+}
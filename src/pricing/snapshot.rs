@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cache::{fetched_at, is_fresh};
+use crate::config::Config;
+use crate::doppler::LOCAL_DOPPLER;
+use crate::items::LOCAL_FILE;
+use crate::patterns::load_patterns;
+use crate::priced_items::{Priced, PricedItems, LOCAL_PRICES};
+use crate::search::SearchIndex;
+use crate::sources::registry;
+use crate::store::ItemStore;
+use crate::Error;
+
+pub const LOCAL_SNAPSHOT: &str = "store.snapshot";
+
+const MAGIC: &[u8; 4] = b"FTHR";
+
+/// Bump whenever `Priced`, `SearchIndex` or anything they contain changes shape, older snapshots are then rebuilt
+const VERSION: u32 = 3;
+
+/// What a snapshot was built from, compared against the current files and settings on load
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct Provenance {
+    catalog_fetched_at: Option<i64>,
+    prices_fetched_at: Option<i64>,
+    doppler_modified_at: Option<i64>,
+    /// Pricing settings and market order that went into every `Priced`
+    settings: String,
+}
+
+impl Provenance {
+    fn current(config: &Config) -> Self {
+        let mut weights: Vec<(&String, &f64)> = config.source_weights.iter().collect();
+        weights.sort_by(|a, b| a.0.cmp(b.0));
+
+        let markets: Vec<&str> = registry().iter().map(|source| source.name()).collect();
+
+        Provenance {
+            catalog_fetched_at: fetched_at(&config.data_path(LOCAL_FILE)),
+            prices_fetched_at: fetched_at(&config.data_path(LOCAL_PRICES)),
+            doppler_modified_at: fetched_at(&config.data_path(LOCAL_DOPPLER)),
+            settings: format!("{} {:?} {:?} {:?}", config.price_strategy, weights, config.outlier_filter, markets),
+        }
+    }
+}
+
+/// Writes the consolidated table and its search index, the trade-up catalog is rebuilt on load
+pub fn save(store: &ItemStore, config: &Config) -> Result<(), Error> {
+    let path = config.data_path(LOCAL_SNAPSHOT);
+    let temp = format!("{}.tmp", path);
+
+    let items: Vec<(&Arc<str>, &Priced)> = store.item_data.iter().collect();
+
+    let mut writer = BufWriter::new(File::create(&temp)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    bincode::serialize_into(&mut writer, &Provenance::current(config))?;
+    bincode::serialize_into(&mut writer, &items)?;
+    bincode::serialize_into(&mut writer, &store.doppler_data)?;
+    bincode::serialize_into(&mut writer, &store.search)?;
+    writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+
+    fs::rename(&temp, &path)?;
+    println!("Wrote {} with {} items", path, items.len());
+
+    Ok(())
+}
+
+/// Loads the last snapshot, failing with the reason when it is missing, from another version,
+/// or built from different data or settings than are on disk now
+pub fn load(config: &Config) -> Result<ItemStore, Error> {
+    let path = config.data_path(LOCAL_SNAPSHOT);
+    let mut reader = BufReader::new(File::open(&path)?);
+
+    let mut magic = [0u8; 4];
+    let mut version = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    reader.read_exact(&mut version)?;
+
+    if &magic != MAGIC {
+        return Err(format!("{} is not a snapshot", path).into());
+    }
+    let version = u32::from_le_bytes(version);
+    if version != VERSION {
+        return Err(format!("{} is version {}, expected {}", path, version, VERSION).into());
+    }
+
+    let provenance: Provenance = bincode::deserialize_from(&mut reader)?;
+    if provenance != Provenance::current(config) {
+        return Err(format!("{} was built from other data or settings", path).into());
+    }
+
+    // Stale data goes through the full path so it gets refreshed, unless there is no refreshing it
+    if !config.offline {
+        for file in [LOCAL_FILE, LOCAL_PRICES] {
+            if !is_fresh(&config.data_path(file), config.max_data_age()) {
+                return Err(format!("{} is older than {}h", file, config.max_data_age().as_secs() / (60 * 60)).into());
+            }
+        }
+    }
+
+    let items: Vec<(Arc<str>, Priced)> = bincode::deserialize_from(&mut reader)?;
+    let doppler_data: HashMap<String, String> = bincode::deserialize_from(&mut reader)?;
+    let mut search: SearchIndex = bincode::deserialize_from(&mut reader)?;
+
+    let item_data: PricedItems = items.into_iter().collect();
+    search.attach(&item_data)?;
+
+    // Small hand-maintained file, read fresh rather than snapshotted
+    Ok(ItemStore::with_search(item_data, doppler_data, load_patterns(config), search, provenance.prices_fetched_at))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::confidence::Confidence;
+    use crate::patterns::PatternTable;
+    use crate::priced_items::Quote;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("feather-snapshot-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn priced(name: &str, price: f64) -> (Arc<str>, Priced) {
+        let quotes: Box<[Quote]> = Box::new([Quote::new(price, 1.0, 0)]);
+        let priced = Priced {
+            info: Arc::new(serde_json::from_value(json!({ "name": name })).unwrap()),
+            confidence: Confidence::compute(&quotes, &[], None),
+            quotes,
+            dropped: Box::new([]),
+            steam_windows: None,
+            phase_fallback: false,
+            feather: Some(price),
+        };
+        (Arc::from(name), priced)
+    }
+
+    fn store() -> ItemStore {
+        let item_data: PricedItems = [
+            priced("AK-47 | Redline (Field-Tested)", 12.5),
+            priced("StatTrak™ AK-47 | Redline (Field-Tested)", 40.0),
+            priced("AWP | Asiimov (Field-Tested)", 95.0),
+        ].into_iter().collect();
+        let doppler_data = HashMap::from([("icon".to_string(), "Phase 2".to_string())]);
+
+        ItemStore::new(item_data, doppler_data, PatternTable::default(), None)
+    }
+
+    #[test]
+    fn round_trips_prices_and_search() {
+        let config = Config::offline(scratch("round-trip"));
+        let saved = store();
+        save(&saved, &config).unwrap();
+
+        let loaded = load(&config).unwrap();
+        assert_eq!(loaded.item_data.len(), saved.item_data.len());
+        for (name, priced) in &saved.item_data {
+            assert_eq!(loaded.item_data[name].feather, priced.feather);
+        }
+        assert_eq!(loaded.doppler_data, saved.doppler_data);
+        assert_eq!(loaded.search.search("redline", 5, |_| true), saved.search.search("redline", 5, |_| true));
+        assert_eq!(loaded.search.search("", 5, |_| true), saved.search.search("", 5, |_| true));
+
+        fs::remove_dir_all(&config.data_dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let config = Config::offline(scratch("version"));
+        save(&store(), &config).unwrap();
+
+        let path = config.data_path(LOCAL_SNAPSHOT);
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(VERSION - 1).to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+
+        let error = load(&config).err().unwrap();
+        assert!(error.to_string().contains(&format!("version {}", VERSION - 1)));
+
+        fs::remove_dir_all(&config.data_dir).unwrap();
+    }
+}
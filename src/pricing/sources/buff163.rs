@@ -0,0 +1,40 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{doppler_price, Doppler, PriceSource};
+
+#[derive(Deserialize, Debug)]
+struct BPrice {
+    price: Option<f64>,
+    doppler: Option<Doppler>
+}
+
+#[derive(Deserialize, Debug)]
+struct BItem {
+    starting_at: Option<BPrice>
+}
+
+pub struct Buff163;
+
+impl PriceSource for Buff163 {
+    fn name(&self) -> &'static str {
+        "buff163"
+    }
+
+    fn label(&self) -> &'static str {
+        "buff.163"
+    }
+
+    fn reputable(&self) -> bool {
+        true
+    }
+
+    fn price(&self, market: &Value, phase: Option<&str>) -> Option<f64> {
+        let starting_at = BItem::deserialize(market).ok()?.starting_at?;
+
+        match phase {
+            Some(phase) => doppler_price(starting_at.doppler.as_ref(), phase),
+            None => starting_at.price,
+        }
+    }
+}
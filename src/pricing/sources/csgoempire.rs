@@ -0,0 +1,24 @@
+use serde_json::Value;
+
+use super::PriceSource;
+
+pub struct CsgoEmpire;
+
+impl PriceSource for CsgoEmpire {
+    fn name(&self) -> &'static str {
+        "csgoempire"
+    }
+
+    fn label(&self) -> &'static str {
+        "CSGOEmpire"
+    }
+
+    fn price(&self, market: &Value, phase: Option<&str>) -> Option<f64> {
+        // CSGOEmpire quotes a single price with no Doppler breakdown
+        if phase.is_some() {
+            return None;
+        }
+
+        market.as_f64()
+    }
+}
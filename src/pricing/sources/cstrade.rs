@@ -0,0 +1,31 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{doppler_price, Doppler, PriceSource};
+
+#[derive(Deserialize, Debug)]
+struct CItem {
+    price: Option<f64>,
+    doppler: Option<Doppler>
+}
+
+pub struct CsTrade;
+
+impl PriceSource for CsTrade {
+    fn name(&self) -> &'static str {
+        "cstrade"
+    }
+
+    fn label(&self) -> &'static str {
+        "CS.TRADE"
+    }
+
+    fn price(&self, market: &Value, phase: Option<&str>) -> Option<f64> {
+        let item = CItem::deserialize(market).ok()?;
+
+        match phase {
+            Some(phase) => doppler_price(item.doppler.as_ref(), phase),
+            None => item.price,
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use serde_json::Value;
+
+use super::PriceSource;
+
+pub struct LootFarm;
+
+impl PriceSource for LootFarm {
+    fn name(&self) -> &'static str {
+        "lootfarm"
+    }
+
+    fn label(&self) -> &'static str {
+        "LOOT.FARM"
+    }
+
+    fn price(&self, market: &Value, phase: Option<&str>) -> Option<f64> {
+        // LOOT.FARM quotes a single price with no Doppler breakdown
+        if phase.is_some() {
+            return None;
+        }
+
+        market.as_f64()
+    }
+}
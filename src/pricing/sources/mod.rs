@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use serde_json::Value;
+
+mod buff163;
+mod csgoempire;
+mod cstrade;
+mod lootfarm;
+mod skinport;
+mod skinwallet;
+mod steam;
+mod swapgg;
+
+pub use steam::SteamWindows;
+
+/// Phase name to price, as found under `doppler` in the feed
+pub type Doppler = HashMap<String, Option<f64>>;
+
+/// A marketplace quoted in prices_v6.json
+pub trait PriceSource: Send + Sync {
+    /// Key of this market inside a feed entry
+    fn name(&self) -> &'static str;
+
+    /// Name shown to users
+    fn label(&self) -> &'static str;
+
+    /// Relative weight of this market in the suggested price
+    fn weight(&self) -> f64 {
+        1.0
+    }
+
+    /// Whether this market is trusted enough for the lowest reputable price strategy
+    fn reputable(&self) -> bool {
+        false
+    }
+
+    /// Parses this market's part of a feed entry, `phase` is set for Doppler items
+    fn price(&self, market: &Value, phase: Option<&str>) -> Option<f64>;
+}
+
+/// Position of a market in [`registry`], stored instead of its name in every quote
+pub type SourceId = u8;
+
+static REGISTRY: OnceLock<Vec<Box<dyn PriceSource>>> = OnceLock::new();
+
+/// Every known market, built once and shared by all consolidations
+pub fn registry() -> &'static [Box<dyn PriceSource>] {
+    REGISTRY.get_or_init(all_sources)
+}
+
+fn all_sources() -> Vec<Box<dyn PriceSource>> {
+    vec![
+        Box::new(steam::Steam),
+        Box::new(skinport::Skinport),
+        Box::new(cstrade::CsTrade),
+        Box::new(buff163::Buff163),
+        Box::new(lootfarm::LootFarm),
+        Box::new(csgoempire::CsgoEmpire),
+        Box::new(swapgg::SwapGg),
+        Box::new(skinwallet::Skinwallet),
+    ]
+}
+
+/// Looks up a phase in an optional Doppler table, ignoring case since feeds and the catalog
+/// don't always agree on it (e.g. `Black Pearl` and `Black pearl`)
+fn doppler_price(doppler: Option<&Doppler>, phase: &str) -> Option<f64> {
+    let doppler = doppler?;
+
+    doppler.get(phase)
+        .or_else(|| doppler
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(phase))
+            .map(|(_, price)| price))
+        .and_then(|doppler_option| *doppler_option)
+}
@@ -0,0 +1,34 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::PriceSource;
+
+#[derive(Deserialize, Debug)]
+struct PItem {
+    starting_at: Option<f64>
+}
+
+pub struct Skinport;
+
+impl PriceSource for Skinport {
+    fn name(&self) -> &'static str {
+        "skinport"
+    }
+
+    fn label(&self) -> &'static str {
+        "Skinport"
+    }
+
+    fn reputable(&self) -> bool {
+        true
+    }
+
+    fn price(&self, market: &Value, phase: Option<&str>) -> Option<f64> {
+        // Skinport does not list Doppler phases separately
+        if phase.is_some() {
+            return None;
+        }
+
+        PItem::deserialize(market).ok()?.starting_at
+    }
+}
@@ -0,0 +1,24 @@
+use serde_json::Value;
+
+use super::PriceSource;
+
+pub struct Skinwallet;
+
+impl PriceSource for Skinwallet {
+    fn name(&self) -> &'static str {
+        "skinwallet"
+    }
+
+    fn label(&self) -> &'static str {
+        "Skinwallet"
+    }
+
+    fn price(&self, market: &Value, phase: Option<&str>) -> Option<f64> {
+        // Skinwallet quotes a single price with no Doppler breakdown
+        if phase.is_some() {
+            return None;
+        }
+
+        market.as_f64()
+    }
+}
@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::PriceSource;
+
+/// Sale windows, most recent first
+const WINDOWS: [&str; 4] = ["last_24h", "last_7d", "last_30d", "last_90d"];
+
+/// Steam Community Market sale price averages, kept single precision since one is stored per item
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SteamWindows {
+    pub last_24h: Option<f32>,
+    pub last_7d: Option<f32>,
+    pub last_30d: Option<f32>,
+    pub last_90d: Option<f32>
+}
+
+impl SteamWindows {
+    pub fn parse(market: &Value) -> Option<Self> {
+        SteamWindows::deserialize(market).ok()
+    }
+}
+
+pub struct Steam;
+
+impl PriceSource for Steam {
+    fn name(&self) -> &'static str {
+        "steam"
+    }
+
+    fn label(&self) -> &'static str {
+        "Steam Market"
+    }
+
+    fn reputable(&self) -> bool {
+        true
+    }
+
+    fn price(&self, market: &Value, phase: Option<&str>) -> Option<f64> {
+        // Steam does not list Doppler phases separately
+        if phase.is_some() {
+            return None;
+        }
+
+        // Read at full precision, the stored `SteamWindows` are only for display and trends
+        WINDOWS.iter().find_map(|window| market.get(window)?.as_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn prices_from_the_most_recent_window() {
+        let market = json!({ "last_24h": null, "last_7d": 1234.56789, "last_30d": 1000.0, "last_90d": 900.0 });
+
+        assert_eq!(Steam.price(&market, None), Some(1234.56789));
+        assert_eq!(Steam.price(&market, Some("Phase 2")), None);
+        assert_eq!(Steam.price(&json!({ "last_24h": null }), None), None);
+    }
+}
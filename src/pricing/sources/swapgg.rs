@@ -0,0 +1,24 @@
+use serde_json::Value;
+
+use super::PriceSource;
+
+pub struct SwapGg;
+
+impl PriceSource for SwapGg {
+    fn name(&self) -> &'static str {
+        "swapgg"
+    }
+
+    fn label(&self) -> &'static str {
+        "swap.gg"
+    }
+
+    fn price(&self, market: &Value, phase: Option<&str>) -> Option<f64> {
+        // swap.gg quotes a single price with no Doppler breakdown
+        if phase.is_some() {
+            return None;
+        }
+
+        market.as_f64()
+    }
+}
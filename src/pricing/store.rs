@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{Mutex, OnceCell, RwLock};
+
+use crate::config::Config;
+use crate::contracts::TradeupCatalog;
+use crate::database::DatabaseManager;
+use crate::history::record_snapshot;
+use crate::cache::{blocking, fetched_at, format_age, is_fresh};
+use crate::health::{Dataset, Health, Status};
+use crate::patterns::{load_patterns, PatternTable};
+use crate::priced_items::{consolidate_prices, refresh_consolidated, PricedItems, LOCAL_PRICES};
+use crate::search::SearchIndex;
+use crate::snapshot;
+use crate::Error;
+
+pub struct ItemStore {
+    pub item_data: PricedItems,
+    pub doppler_data: HashMap<String, String>,
+    pub patterns: PatternTable,
+    /// Autocomplete lookup, sharing its names with the keys of `item_data`
+    pub search: SearchIndex,
+    pub tradeups: TradeupCatalog,
+    /// Unix seconds when the price feed was downloaded
+    pub prices_fetched_at: Option<i64>,
+}
+
+/// Readers clone the inner `Arc` and keep a consistent snapshot for the whole command,
+/// while the refresh task swaps in a new one
+pub type SharedStore = Arc<RwLock<Arc<ItemStore>>>;
+
+impl ItemStore {
+    pub fn new(item_data: PricedItems, doppler_data: HashMap<String, String>, patterns: PatternTable, prices_fetched_at: Option<i64>) -> Self {
+        let search = SearchIndex::build(item_data.keys().cloned().collect(), &item_data);
+        Self::with_search(item_data, doppler_data, patterns, search, prices_fetched_at)
+    }
+
+    /// Like `new`, reusing a search index built over `item_data` earlier
+    pub fn with_search(
+        item_data: PricedItems,
+        doppler_data: HashMap<String, String>,
+        patterns: PatternTable,
+        search: SearchIndex,
+        prices_fetched_at: Option<i64>,
+    ) -> Self {
+        let tradeups = TradeupCatalog::build(&item_data);
+
+        Self {
+            item_data,
+            doppler_data,
+            patterns,
+            search,
+            tradeups,
+            prices_fetched_at,
+        }
+    }
+}
+
+/// Starts from the binary snapshot when it matches the data on disk, otherwise consolidates the feeds
+pub async fn load_store(config: &Config) -> Result<ItemStore, Error> {
+    let snapshot_config = config.clone();
+    match blocking(move || snapshot::load(&snapshot_config)).await {
+        Ok(store) => {
+            println!("Loaded {} items from snapshot", store.item_data.len());
+            return Ok(store);
+        },
+        Err(e) => println!("Not using snapshot: {}", e),
+    }
+
+    let (item_data, doppler_data) = consolidate_prices(config).await?;
+    build_store(item_data, doppler_data, config).await
+}
+
+/// Re-downloads the feeds into a new store, leaving the current one untouched on failure
+async fn refresh_store(config: &Config) -> Result<ItemStore, Error> {
+    let (item_data, doppler_data) = refresh_consolidated(config).await?;
+    build_store(item_data, doppler_data, config).await
+}
+
+/// Builds the indexes over consolidated prices and writes the snapshot, both off the async workers
+async fn build_store(item_data: PricedItems, doppler_data: HashMap<String, String>, config: &Config) -> Result<ItemStore, Error> {
+    let config = config.clone();
+    blocking(move || {
+        let store = ItemStore::new(item_data, doppler_data, load_patterns(&config), fetched_at(&config.data_path(LOCAL_PRICES)));
+        save_snapshot(&store, &config);
+        Ok(store)
+    }).await
+}
+
+/// Reports the price and Doppler datasets behind a freshly loaded store
+pub fn report_health(store: &ItemStore, config: &Config, health: &Health) {
+    let prices = if !store.item_data.values().any(|priced| priced.feather.is_some()) {
+        Status::Failed("no item has a price".to_string())
+    } else if !is_fresh(&config.data_path(LOCAL_PRICES), config.max_data_age()) {
+        Status::Degraded(format!("last updated {}", format_age(store.prices_fetched_at)))
+    } else {
+        Status::Ready
+    };
+    health.set(Dataset::Prices, prices);
+
+    let dopplers = if store.doppler_data.is_empty() {
+        Status::Degraded("no phase icons, Dopplers are valued at the base price".to_string())
+    } else {
+        Status::Ready
+    };
+    health.set(Dataset::Dopplers, dopplers);
+}
+
+fn save_snapshot(store: &ItemStore, config: &Config) {
+    if let Err(e) = snapshot::save(store, config) {
+        eprintln!("Failed to write snapshot: {}", e);
+    }
+}
+
+/// Periodically re-downloads the feeds, recording price history once the database is connected
+/// and after every period, offline bots included
+pub fn spawn_refresh_task(store: SharedStore, config: Config, db: Arc<OnceCell<Arc<Mutex<DatabaseManager>>>>, health: Arc<Health>) {
+    tokio::spawn(async move {
+        let period = Duration::from_secs(config.price_refresh_hours * 60 * 60);
+        let mut interval = tokio::time::interval(period);
+
+        // First tick completes immediately, startup has already loaded the data
+        interval.tick().await;
+
+        // Items usually load before the database connects, record the startup prices once it does
+        {
+            let store = store.clone();
+            let db = db.clone();
+            let health = health.clone();
+            tokio::spawn(async move {
+                health.ready(Dataset::Database).await;
+                record_history(&store, &db).await;
+            });
+        }
+
+        loop {
+            interval.tick().await;
+
+            // Offline bots keep serving the local files they started with
+            if config.offline {
+                record_history(&store, &db).await;
+                continue;
+            }
+
+            match refresh_store(&config).await {
+                Ok(fresh) => {
+                    let fresh = Arc::new(fresh);
+                    report_health(&fresh, &config, &health);
+                    *store.write().await = fresh;
+                    println!("Swapped in refreshed item data");
+
+                    record_history(&store, &db).await;
+                },
+                Err(e) => {
+                    // Keep serving the previous snapshot until the next attempt
+                    eprintln!("Failed to refresh items: {}", e);
+
+                    let prices_fetched_at = store.read().await.prices_fetched_at;
+                    health.set(Dataset::Prices, Status::Degraded(format!(
+                        "refresh failed, serving prices from {}",
+                        format_age(prices_fetched_at)
+                    )));
+                }
+            }
+        }
+    });
+}
+
+/// Records the prices being served as today's history, once the database is connected
+async fn record_history(store: &SharedStore, db: &OnceCell<Arc<Mutex<DatabaseManager>>>) {
+    let Some(db) = db.get() else {
+        println!("Database not connected yet, skipping price history");
+        return;
+    };
+    let current = store.read().await.clone();
+
+    if let Err(e) = record_snapshot(&current, db).await {
+        eprintln!("Failed to record price history: {}", e);
+    }
+}
@@ -0,0 +1,214 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::priced_items::Quote;
+
+/// How market quotes are combined into the suggested price
+#[derive(Clone, Debug, PartialEq)]
+pub enum Strategy {
+    PowerMean(f64),
+    Median,
+    /// Fraction of quotes dropped from each end before averaging
+    TrimmedMean(f64),
+    WeightedMean,
+    MinReputable,
+}
+
+impl Default for Strategy {
+    fn default() -> Self {
+        Strategy::PowerMean(-3.0)
+    }
+}
+
+/// Example values offered when configuring a strategy
+pub const STRATEGY_EXAMPLES: [&str; 5] = [
+    "power_mean:-3",
+    "median",
+    "trimmed_mean:0.2",
+    "weighted_mean",
+    "min_reputable",
+];
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, arg) = match s.trim().split_once(':') {
+            Some((name, arg)) => (name, Some(arg.trim())),
+            None => (s.trim(), None),
+        };
+
+        let parse_arg = |default: f64| match arg {
+            Some(arg) => arg.parse::<f64>()
+                .ok()
+                .filter(|arg| arg.is_finite())
+                .ok_or(format!("Invalid strategy argument `{}`", arg)),
+            None => Ok(default),
+        };
+
+        match name.to_lowercase().as_str() {
+            "power_mean" => Ok(Strategy::PowerMean(parse_arg(-3.0)?)),
+            "median" => Ok(Strategy::Median),
+            "trimmed_mean" => {
+                let fraction = parse_arg(0.2)?;
+                if !(0.0..0.5).contains(&fraction) {
+                    return Err("Trim fraction must be in [0, 0.5)".to_string());
+                }
+                Ok(Strategy::TrimmedMean(fraction))
+            },
+            "weighted_mean" => Ok(Strategy::WeightedMean),
+            "min_reputable" => Ok(Strategy::MinReputable),
+            _ => Err(format!("Unknown strategy `{}`", name)),
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strategy::PowerMean(power) => write!(f, "Power mean (p = {})", power),
+            Strategy::Median => write!(f, "Median"),
+            Strategy::TrimmedMean(fraction) => write!(f, "Trimmed mean ({:.0}%)", fraction * 100.0),
+            Strategy::WeightedMean => write!(f, "Weighted mean"),
+            Strategy::MinReputable => write!(f, "Lowest reputable market"),
+        }
+    }
+}
+
+fn sorted_prices(quotes: &[Quote]) -> Vec<f64> {
+    let mut prices: Vec<f64> = quotes.iter().map(|quote| quote.price).collect();
+    prices.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    prices
+}
+
+fn median(sorted: &[f64]) -> Option<f64> {
+    let n = sorted.len();
+
+    match n {
+        0 => None,
+        _ if n % 2 == 1 => Some(sorted[n / 2]),
+        _ => Some((sorted[n / 2 - 1] + sorted[n / 2]) / 2.0),
+    }
+}
+
+impl Strategy {
+    pub fn aggregate(&self, quotes: &[Quote]) -> Option<f64> {
+        if quotes.is_empty() {
+            return None;
+        }
+
+        match self {
+            Strategy::PowerMean(power) => {
+                let total_weight: f64 = quotes.iter().map(|quote| f64::from(quote.weight)).sum();
+                if total_weight <= 0.0 {
+                    return None;
+                }
+
+                if *power == 0.0 {
+                    // Limit of the power mean is the geometric mean
+                    let sum: f64 = quotes.iter().map(|quote| f64::from(quote.weight) * quote.price.ln()).sum();
+                    return Some((sum / total_weight).exp());
+                }
+
+                let sum: f64 = quotes.iter().map(|quote| f64::from(quote.weight) * quote.price.powf(*power)).sum();
+                Some((sum / total_weight).powf(1.0 / power))
+            },
+            Strategy::Median => median(&sorted_prices(quotes)),
+            Strategy::TrimmedMean(fraction) => {
+                let sorted = sorted_prices(quotes);
+                let trim = (sorted.len() as f64 * fraction).floor() as usize;
+                let kept = &sorted[trim..sorted.len() - trim];
+
+                if kept.is_empty() {
+                    median(&sorted)
+                } else {
+                    Some(kept.iter().sum::<f64>() / kept.len() as f64)
+                }
+            },
+            Strategy::WeightedMean => {
+                let total_weight: f64 = quotes.iter().map(|quote| f64::from(quote.weight)).sum();
+                if total_weight <= 0.0 {
+                    return None;
+                }
+
+                Some(quotes.iter().map(|quote| f64::from(quote.weight) * quote.price).sum::<f64>() / total_weight)
+            },
+            Strategy::MinReputable => quotes
+                .iter()
+                .filter(|quote| quote.reputable())
+                .map(|quote| quote.price)
+                .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Steam, Skinport and buff.163 are reputable, CS.TRADE and LOOT.FARM are not
+    fn quotes() -> Vec<Quote> {
+        vec![
+            Quote::new(50.0, 1.0, 0),
+            Quote::new(20.0, 1.0, 1),
+            Quote::new(10.0, 2.0, 2),
+            Quote::new(30.0, 1.0, 3),
+            Quote::new(1000.0, 0.0, 4),
+        ]
+    }
+
+    fn aggregate(strategy: &str) -> f64 {
+        strategy.parse::<Strategy>().unwrap().aggregate(&quotes()).unwrap()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn median_ignores_weights() {
+        assert_close(aggregate("median"), 30.0);
+    }
+
+    #[test]
+    fn trimmed_mean_drops_both_ends() {
+        assert_close(aggregate("trimmed_mean:0.2"), 100.0 / 3.0);
+        assert_close(aggregate("trimmed_mean:0"), 1110.0 / 5.0);
+    }
+
+    #[test]
+    fn weighted_mean_uses_weights() {
+        assert_close(aggregate("weighted_mean"), 120.0 / 5.0);
+    }
+
+    #[test]
+    fn power_mean_uses_weights() {
+        assert_close(aggregate("power_mean:1"), 120.0 / 5.0);
+        assert_close(aggregate("power_mean:0"), (50.0f64 * 20.0 * 10.0 * 10.0 * 30.0).powf(0.2));
+
+        let harmonic_leaning = aggregate("power_mean:-3");
+        assert!(harmonic_leaning > 10.0 && harmonic_leaning < aggregate("power_mean:0"));
+    }
+
+    #[test]
+    fn min_reputable_skips_other_markets() {
+        assert_close(aggregate("min_reputable"), 20.0);
+    }
+
+    #[test]
+    fn nothing_to_aggregate() {
+        assert_eq!(Strategy::Median.aggregate(&[]), None);
+        assert_eq!(Strategy::WeightedMean.aggregate(&[Quote::new(10.0, 0.0, 0)]), None);
+        assert_eq!(Strategy::MinReputable.aggregate(&[Quote::new(10.0, 1.0, 2)]), None);
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!("power_mean:NaN".parse::<Strategy>().is_err());
+        assert!("power_mean:inf".parse::<Strategy>().is_err());
+        assert!("trimmed_mean:0.5".parse::<Strategy>().is_err());
+        assert!("mode".parse::<Strategy>().is_err());
+        assert_eq!("power_mean".parse(), Ok(Strategy::PowerMean(-3.0)));
+    }
+}
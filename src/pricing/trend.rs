@@ -0,0 +1,154 @@
+use std::fmt;
+
+use crate::sources::SteamWindows;
+
+/// Coefficient of variation across the Steam windows above which an item counts as volatile
+const HIGH_VOLATILITY: f64 = 0.15;
+const MEDIUM_VOLATILITY: f64 = 0.05;
+
+/// Price movement derived from the Steam sale windows
+#[derive(Debug, Clone, Copy)]
+pub struct SteamTrend {
+    /// Change of the 24 hour average against the 7 day average
+    pub day_vs_week: Option<f64>,
+    /// Change of the 7 day average against the 30 day average
+    pub week_vs_month: Option<f64>,
+    /// Coefficient of variation of all available windows
+    pub volatility: Option<f64>,
+}
+
+fn change(recent: Option<f64>, base: Option<f64>) -> Option<f64> {
+    match (recent, base) {
+        (Some(recent), Some(base)) if recent > 0.0 && base > 0.0 => Some((recent - base) / base),
+        _ => None,
+    }
+}
+
+impl SteamTrend {
+    pub fn compute(windows: &SteamWindows) -> Self {
+        let values: Vec<f64> = [windows.last_24h, windows.last_7d, windows.last_30d, windows.last_90d]
+            .iter()
+            .filter_map(|&price| price.map(f64::from))
+            .filter(|&price| price > 0.0)
+            .collect();
+
+        let volatility = if values.len() >= 2 {
+            let n = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / n;
+            let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+            Some(variance.sqrt() / mean)
+        } else {
+            None
+        };
+
+        SteamTrend {
+            day_vs_week: change(windows.last_24h.map(f64::from), windows.last_7d.map(f64::from)),
+            week_vs_month: change(windows.last_7d.map(f64::from), windows.last_30d.map(f64::from)),
+            volatility,
+        }
+    }
+
+    pub fn volatility_label(&self) -> Option<&'static str> {
+        self.volatility.map(|volatility| {
+            if volatility >= HIGH_VOLATILITY {
+                "High"
+            } else if volatility >= MEDIUM_VOLATILITY {
+                "Medium"
+            } else {
+                "Low"
+            }
+        })
+    }
+}
+
+fn arrow(change: f64) -> &'static str {
+    if change > 0.0 {
+        ":small_red_triangle:"
+    } else if change < 0.0 {
+        ":small_red_triangle_down:"
+    } else {
+        ":heavy_minus_sign:"
+    }
+}
+
+impl fmt::Display for SteamTrend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts: Vec<String> = vec![];
+
+        if let Some(change) = self.day_vs_week {
+            parts.push(format!("{} {:+.1}% 24h", arrow(change), change * 100.0));
+        }
+        if let Some(change) = self.week_vs_month {
+            parts.push(format!("{} {:+.1}% 7d", arrow(change), change * 100.0));
+        }
+        if let Some(label) = self.volatility_label() {
+            parts.push(format!("{} volatility", label));
+        }
+
+        write!(f, "{}", parts.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trend(last_24h: Option<f32>, last_7d: Option<f32>, last_30d: Option<f32>, last_90d: Option<f32>) -> SteamTrend {
+        SteamTrend::compute(&SteamWindows { last_24h, last_7d, last_30d, last_90d })
+    }
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.unwrap();
+        assert!((actual - expected).abs() < 1e-6, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn rising() {
+        let trend = trend(Some(11.0), Some(10.0), Some(8.0), None);
+
+        assert_close(trend.day_vs_week, 0.1);
+        assert_close(trend.week_vs_month, 0.25);
+        assert!(trend.to_string().starts_with(":small_red_triangle: +10.0% 24h"));
+    }
+
+    #[test]
+    fn falling() {
+        let trend = trend(Some(9.0), Some(10.0), Some(12.5), Some(12.5));
+
+        assert_close(trend.day_vs_week, -0.1);
+        assert_close(trend.week_vs_month, -0.2);
+        assert!(trend.to_string().starts_with(":small_red_triangle_down: -10.0% 24h"));
+    }
+
+    #[test]
+    fn flat() {
+        let trend = trend(Some(10.0), Some(10.0), Some(10.0), Some(10.0));
+
+        assert_eq!(trend.day_vs_week, Some(0.0));
+        assert_eq!(trend.volatility, Some(0.0));
+        assert_eq!(trend.volatility_label(), Some("Low"));
+        assert!(trend.to_string().starts_with(":heavy_minus_sign: +0.0% 24h"));
+    }
+
+    #[test]
+    fn volatility_thresholds() {
+        assert_eq!(trend(Some(10.25), Some(9.75), None, None).volatility_label(), Some("Low"));
+        assert_eq!(trend(Some(10.5), Some(9.5), None, None).volatility_label(), Some("Medium"));
+        assert_eq!(trend(Some(11.5), Some(8.5), None, None).volatility_label(), Some("High"));
+    }
+
+    #[test]
+    fn missing_windows() {
+        let only_day = trend(Some(10.0), None, None, None);
+        assert_eq!(only_day.day_vs_week, None);
+        assert_eq!(only_day.week_vs_month, None);
+        assert_eq!(only_day.volatility, None);
+        assert_eq!(only_day.to_string(), "");
+
+        // A window without sales reports 0 rather than nothing
+        let no_week_sales = trend(Some(10.0), Some(0.0), Some(8.0), None);
+        assert_eq!(no_week_sales.day_vs_week, None);
+        assert_eq!(no_week_sales.week_vs_month, None);
+        assert_close(no_week_sales.volatility, 1.0 / 9.0);
+    }
+}
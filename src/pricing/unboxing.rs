@@ -0,0 +1,306 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::doppler::phased_name;
+use crate::items::{variant_name, Drop, Item, WEARS};
+use crate::store::ItemStore;
+use crate::strategy::Strategy;
+
+/// Chance of a rare special item (knife or gloves) per opening
+const RARE_SPECIAL_CHANCE: f64 = 0.0026;
+
+/// Each grade is this many times rarer than the one below it, which gives Valve's published
+/// 79.92% / 15.98% / 3.2% / 0.64% for a regular case
+const GRADE_RATIO: f64 = 5.0;
+
+/// Chance of a StatTrak copy in containers that have them
+pub const STATTRAK_CHANCE: f64 = 0.1;
+
+/// Steam store price of a case key in USD
+pub const KEY_PRICE: f64 = 2.49;
+
+/// One exact item an opening can produce
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub name: String,
+    pub price: f64,
+    /// Chance per opening
+    pub chance: f64,
+}
+
+/// Odds and average value of one grade of drops
+#[derive(Debug, Clone)]
+pub struct GradeValue {
+    pub name: String,
+    /// Chance per opening
+    pub chance: f64,
+    /// Average value of a drop of this grade, `None` when none of its drops are priced
+    pub average: Option<f64>,
+}
+
+#[derive(Debug)]
+pub struct CaseValue {
+    /// Value of an average opening, over the grades that could be priced
+    pub expected: f64,
+    pub case_price: Option<f64>,
+    /// `None` for containers opened without a key
+    pub key_price: Option<f64>,
+    /// Highest grade first, rare special items on top
+    pub grades: Vec<GradeValue>,
+    /// Most valuable first
+    pub outcomes: Vec<Outcome>,
+    pub stattrak: bool,
+    pub priced_drops: usize,
+    pub total_drops: usize,
+}
+
+impl CaseValue {
+    /// Price of the container plus its key
+    pub fn cost(&self) -> Option<f64> {
+        self.case_price.map(|price| price + self.key_price.unwrap_or(0.0))
+    }
+
+    /// Return on one opening, `0.1` meaning 10% profit
+    pub fn roi(&self) -> Option<f64> {
+        self.cost()
+            .filter(|&cost| cost > 0.0)
+            .map(|cost| self.expected / cost - 1.0)
+    }
+
+    /// Chance that an opening is worth more than it cost
+    pub fn profit_chance(&self) -> Option<f64> {
+        let cost = self.cost()?;
+        Some(self.outcomes
+            .iter()
+            .filter(|outcome| outcome.price > cost)
+            .map(|outcome| outcome.chance)
+            .sum())
+    }
+}
+
+/// Share of a uniform float range that falls into each exterior
+fn wear_chances(min: f64, max: f64) -> Vec<(&'static str, f64)> {
+    if max <= min {
+        return vec![];
+    }
+
+    WEARS
+        .iter()
+        .map(|&(wear, low, high)| (wear, (high.min(max) - low.max(min)).max(0.0) / (max - min)))
+        .filter(|&(_, chance)| chance > 0.0)
+        .collect()
+}
+
+/// Float range of a drop, taken from whichever exterior of it is in the catalog
+fn float_range(drop: &Drop, store: &ItemStore) -> Option<(f64, f64)> {
+    WEARS.iter().find_map(|(wear, _, _)| {
+        let info = &store.item_data.get(format!("{} ({})", drop.name, wear).as_str())?.info;
+        Some((info.min_float?, info.max_float?))
+    })
+}
+
+fn price_of(name: &str, phase: Option<&str>, store: &ItemStore, strategy: Option<&Strategy>) -> Option<f64> {
+    let priced = phase
+        .and_then(|phase| store.item_data.get(phased_name(name, phase).as_str()))
+        .or_else(|| store.item_data.get(name))?;
+
+    priced.suggested(strategy).filter(|&price| price > 0.0)
+}
+
+/// Every priced exterior and variant of a drop, with chances relative to the drop itself.
+/// Variants missing from the catalog (e.g. StatTrak gloves) can't drop, so the rest are rescaled.
+fn drop_outcomes(drop: &Drop, variants: &[(&str, f64)], store: &ItemStore, strategy: Option<&Strategy>) -> Vec<Outcome> {
+    let wears: Vec<(String, f64)> = match float_range(drop, store) {
+        Some((min, max)) => wear_chances(min, max)
+            .into_iter()
+            .map(|(wear, chance)| (format!("{} ({})", drop.name, wear), chance))
+            .collect(),
+        None => vec![(drop.name.clone(), 1.0)],
+    };
+
+    let mut outcomes: Vec<Outcome> = vec![];
+
+    for (prefix, variant_chance) in variants {
+        for (name, wear_chance) in &wears {
+            let name = variant_name(name, prefix);
+            if let Some(price) = price_of(&name, drop.phase.as_deref(), store, strategy) {
+                let name = match &drop.phase {
+                    Some(phase) => phased_name(&name, phase),
+                    None => name,
+                };
+                outcomes.push(Outcome { name, price, chance: variant_chance * wear_chance });
+            }
+        }
+    }
+
+    let total: f64 = outcomes.iter().map(|outcome| outcome.chance).sum();
+    for outcome in &mut outcomes {
+        outcome.chance /= total;
+    }
+
+    outcomes
+}
+
+/// Values a group of equally likely drops that together have `chance` per opening
+fn value_grade(
+    name: String,
+    drops: &[&Drop],
+    chance: f64,
+    variants: &[(&str, f64)],
+    store: &ItemStore,
+    strategy: Option<&Strategy>,
+    outcomes: &mut Vec<Outcome>,
+) -> (GradeValue, usize) {
+    let priced: Vec<Vec<Outcome>> = drops
+        .iter()
+        .map(|drop| drop_outcomes(drop, variants, store, strategy))
+        .filter(|drop_outcomes| !drop_outcomes.is_empty())
+        .collect();
+
+    let average = if priced.is_empty() {
+        None
+    } else {
+        let drop_chance = chance / priced.len() as f64;
+        let mut total = 0.0;
+
+        for drop_outcomes in &priced {
+            for outcome in drop_outcomes {
+                total += outcome.price * outcome.chance;
+                outcomes.push(Outcome { chance: outcome.chance * drop_chance, ..outcome.clone() });
+            }
+        }
+
+        Some(total / priced.len() as f64)
+    };
+
+    (GradeValue { name, chance, average }, priced.len())
+}
+
+/// Expected value of opening `container` at the official odds
+pub fn evaluate(container: &Item, store: &ItemStore, strategy: Option<&Strategy>) -> CaseValue {
+    let container_name = container.market_hash_name.as_deref().or(container.name.as_deref()).unwrap_or_default();
+
+    let mut grades: BTreeMap<u8, (String, Vec<&Drop>)> = BTreeMap::new();
+    for drop in &container.contains {
+        let Some(rarity) = &drop.rarity else { continue };
+        let Some(grade) = rarity.grade() else { continue };
+
+        grades.entry(grade)
+            .or_insert_with(|| (rarity.name.clone().unwrap_or_default(), vec![]))
+            .1
+            .push(drop);
+    }
+
+    // Souvenir packages only drop souvenirs, cases drop StatTrak where the catalog has it
+    let souvenir = container_name.starts_with("Souvenir");
+    let stattrak = !souvenir && container.contains.iter().any(|drop| WEARS.iter().any(|(wear, _, _)| {
+        let name = variant_name(&format!("{} ({})", drop.name, wear), "StatTrak™ ");
+        store.item_data.contains_key(name.as_str())
+    }));
+    let variants: Vec<(&str, f64)> = if souvenir {
+        vec![("Souvenir ", 1.0)]
+    } else if stattrak {
+        vec![("", 1.0 - STATTRAK_CHANCE), ("StatTrak™ ", STATTRAK_CHANCE)]
+    } else {
+        vec![("", 1.0)]
+    };
+
+    let special_chance = if container.contains_rare.is_empty() { 0.0 } else { RARE_SPECIAL_CHANCE };
+    let weights: Vec<f64> = (0..grades.len()).map(|i| GRADE_RATIO.powi(-(i as i32))).collect();
+    let weight_total: f64 = weights.iter().sum();
+
+    let mut outcomes: Vec<Outcome> = vec![];
+    let mut grade_values: Vec<GradeValue> = vec![];
+    let mut priced_drops = 0;
+
+    for ((_, (name, drops)), weight) in grades.iter().zip(&weights) {
+        let chance = (1.0 - special_chance) * weight / weight_total;
+        let (value, priced) = value_grade(name.clone(), drops, chance, &variants, store, strategy, &mut outcomes);
+        grade_values.push(value);
+        priced_drops += priced;
+    }
+
+    if special_chance > 0.0 {
+        let drops: Vec<&Drop> = container.contains_rare.iter().collect();
+        let (value, priced) = value_grade("Rare Special Item".to_string(), &drops, special_chance, &variants, store, strategy, &mut outcomes);
+        grade_values.push(value);
+        priced_drops += priced;
+    }
+
+    grade_values.reverse();
+    outcomes.sort_by(|a, b| b.price.partial_cmp(&a.price).unwrap_or(Ordering::Equal));
+
+    let expected = grade_values
+        .iter()
+        .filter_map(|grade| grade.average.map(|average| average * grade.chance))
+        .sum();
+
+    CaseValue {
+        expected,
+        case_price: price_of(container_name, None, store, strategy),
+        key_price: (container.kind.as_deref() == Some("Case")).then_some(KEY_PRICE),
+        grades: grade_values,
+        outcomes,
+        stattrak,
+        priced_drops,
+        total_drops: container.contains.len() + container.contains_rare.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::patterns::PatternTable;
+    use crate::priced_items::PricedItems;
+
+    fn drop(name: &str, rarity: &str) -> serde_json::Value {
+        json!({ "name": name, "rarity": { "id": rarity } })
+    }
+
+    #[test]
+    fn wear_chances_follow_the_float_range() {
+        let full = wear_chances(0.0, 1.0);
+        assert_eq!(full.len(), 5);
+        assert!((full[0].1 - 0.07).abs() < 1e-9);
+        assert!((full[4].1 - 0.55).abs() < 1e-9);
+
+        let clipped = wear_chances(0.1, 0.3);
+        assert_eq!(clipped.iter().map(|(wear, _)| *wear).collect::<Vec<_>>(), vec!["Minimal Wear", "Field-Tested"]);
+        assert!((clipped[0].1 - 0.25).abs() < 1e-9);
+        assert!((clipped[1].1 - 0.75).abs() < 1e-9);
+
+        assert!(wear_chances(0.5, 0.5).is_empty());
+    }
+
+    #[test]
+    fn case_odds_match_valve() {
+        let case: Item = serde_json::from_value(json!({
+            "name": "Test Case",
+            "type": "Case",
+            "contains": [
+                drop("Gun | Mil-Spec", "rarity_rare_weapon"),
+                drop("Gun | Restricted", "rarity_mythical_weapon"),
+                drop("Gun | Classified", "rarity_legendary_weapon"),
+                drop("Gun | Covert", "rarity_ancient_weapon"),
+            ],
+            "contains_rare": [drop("★ Knife | Vanilla", "rarity_ancient_weapon")],
+        })).unwrap();
+
+        let store = ItemStore::new(PricedItems::new(), HashMap::new(), PatternTable::default(), None);
+        let value = evaluate(&case, &store, None);
+
+        let chances: Vec<f64> = value.grades.iter().map(|grade| grade.chance * 100.0).collect();
+        for (chance, valve) in chances.iter().zip([0.26, 0.64, 3.2, 15.98, 79.92]) {
+            assert!((chance - valve).abs() < 0.01, "{} is not {}", chance, valve);
+        }
+        assert!((chances.iter().sum::<f64>() - 100.0).abs() < 1e-9);
+
+        assert_eq!(value.key_price, Some(KEY_PRICE));
+        assert_eq!(value.priced_drops, 0);
+        assert_eq!(value.total_drops, 5);
+    }
+}